@@ -1,23 +1,26 @@
-#![feature(iter_next_chunk)]
-
 pub use aoc_2024::prelude::*;
 
 fn main() -> Result<()> {
-    let c = chal()?;
-    let input = c
-        .input
-        .lines()
-        .map(|l| {
-            let l = l?;
-            let [a, b] = l
-                .split_whitespace()
-                .next_chunk()
-                .map_err(|_| anyhow!("parse error"))?;
-            Ok((a.parse::<u32>()?, b.parse::<u32>()?))
-        })
-        .map(Result::unwrap);
+    let c = chal_gen(|rng, size, _, out| {
+        for _ in 0..size {
+            writeln!(
+                out,
+                "{}   {}",
+                rng.range(10000..100000),
+                rng.range(10000..100000)
+            )?;
+        }
+        Ok(())
+    })?;
+
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    c.input.for_each_line(|line| {
+        let [x, y] = line.int_array::<u32, 2>()?;
+        a.push(x);
+        b.push(y);
+        Ok(())
+    })?;
 
-    let (mut a, mut b): (Vec<_>, Vec<_>) = input.unzip();
     a.sort();
     b.sort();
 
@@ -1,7 +1,24 @@
 pub use aoc_2024::prelude::*;
+use aoc_2024::reference;
 
 fn main() -> Result<()> {
-    let c = chal()?;
+    let c = chal_check(
+        |rng, size, _, out| {
+            let stones = (0..size).map(|_| {
+                let digits = 1 + rng.below(6) as u32;
+                rng.below(10u64.pow(digits))
+            });
+            writeln!(out, "{}", stones.format(" "))?;
+            Ok(())
+        },
+        |input, part2, _| {
+            // The stones grow exponentially, so listing them all is only feasible for part 1.
+            Ok((!part2)
+                .then(|| reference::day11(input, 25))
+                .transpose()?
+                .map(|n| n.to_string()))
+        },
+    )?;
     let input = c.input.lines().map(Result::unwrap).next().unwrap();
 
     let stones = input.split_whitespace().map(|n| n.parse::<u64>().unwrap());
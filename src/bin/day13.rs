@@ -1,5 +1,5 @@
 pub use aoc_2024::prelude::*;
-use regex::Regex;
+use aoc_2024::{par, reference};
 
 struct Machine {
     a: (i64, i64),
@@ -36,50 +36,53 @@ impl Machine {
 }
 
 fn main() -> Result<()> {
-    let c = chal()?;
-    let mut input = c.input.lines().map(Result::unwrap).peekable();
-
-    let a_re = Regex::new(r"^Button A: X\+(\d+), Y\+(\d+)$")?;
-    let b_re = Regex::new(r"^Button B: X\+(\d+), Y\+(\d+)$")?;
-    let prize_re = Regex::new(r"^Prize: X=(\d+), Y=(\d+)$")?;
-    let mut cost = 0;
-    loop {
-        let (a, b, target) = &(
-            input.next().ok_or_else(|| anyhow!("unexpected EOF"))?,
-            input.next().ok_or_else(|| anyhow!("unexpected EOF"))?,
-            input.next().ok_or_else(|| anyhow!("unexpected EOF"))?,
-        );
-
-        let [ax, ay] = a_re
-            .captures(a)
-            .ok_or_else(|| anyhow!("invalid input A: {a}"))?
-            .extract()
-            .1;
-        let [bx, by] = b_re
-            .captures(b)
-            .ok_or_else(|| anyhow!("invalid input B: {b}"))?
-            .extract()
-            .1;
-        let [tx, ty] = prize_re
-            .captures(target)
-            .ok_or_else(|| anyhow!("invalid input prize: {target}"))?
-            .extract()
-            .1;
+    let c = chal_check(
+        |rng, size, _, out| {
+            for i in 0..size {
+                let [ax, ay, bx, by] = [(); 4].map(|_| rng.range(10..100));
+                if ax * by == ay * bx {
+                    // Puzzle inputs never have buttons moving in the same direction.
+                    continue;
+                }
+                // Only about half of the prizes can be won.
+                let (x, y) = if rng.chance(0.5) {
+                    let (a, b) = (rng.range(0..101), rng.range(0..101));
+                    (a * ax + b * bx, a * ay + b * by)
+                } else {
+                    (rng.range(1000..20000), rng.range(1000..20000))
+                };
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "Button A: X+{ax}, Y+{ay}")?;
+                writeln!(out, "Button B: X+{bx}, Y+{by}")?;
+                writeln!(out, "Prize: X={x}, Y={y}")?;
+            }
+            Ok(())
+        },
+        |input, part2, _| {
+            // Part 2 needs far too many presses to search.
+            Ok((!part2)
+                .then(|| reference::day13(input))
+                .transpose()?
+                .map(|n| n.to_string()))
+        },
+    )?;
+    let costs = par::try_map(&c.input.sections()?, |section| {
+        section.expect_lines(3)?;
+        let [a, b, target] = [0, 1, 2].map(|i| section.lines[i].int_array());
+        let ([ax, ay], [bx, by], [x, y]) = (a?, b?, target?);
 
-        cost += Machine {
-            a: (ax.parse()?, ay.parse()?),
-            b: (bx.parse()?, by.parse()?),
-            target: (tx.parse()?, ty.parse()?),
+        Ok(Machine {
+            a: (ax, ay),
+            b: (bx, by),
+            target: (x, y),
         }
         .solve(c.part2)
-        .unwrap_or(0);
-
-        if input.next().is_none() {
-            break;
-        }
-    }
+        .unwrap_or(0))
+    })?;
 
-    println!("{cost}");
+    println!("{}", costs.into_iter().sum::<i64>());
 
     Ok(())
 }
@@ -1,13 +1,12 @@
-use std::collections::{HashSet, hash_map::Entry};
+use std::collections::HashSet;
 
 pub use aoc_2024::prelude::*;
 use flate2::{
     Compression,
     bufread::{GzDecoder, GzEncoder},
 };
-use regex::Regex;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 struct Robot {
     p: (i32, i32),
     v: (i32, i32),
@@ -56,20 +55,31 @@ fn stringify_grid(robots: &[Robot]) -> String {
 }
 
 fn main() -> Result<()> {
-    let c = chal()?;
-    let input = c.input.lines().map(Result::unwrap);
+    let c = chal_gen(|rng, size, _, out| {
+        for _ in 0..size {
+            writeln!(
+                out,
+                "p={},{} v={},{}",
+                rng.range(0..WIDTH as i64),
+                rng.range(0..HEIGHT as i64),
+                rng.range(-100..101),
+                rng.range(-100..101)
+            )?;
+        }
+        Ok(())
+    })?;
+    let lines: Vec<Line> = c.input.numbered_lines().try_collect()?;
 
-    let regex = Regex::new(r"^p=(\d+),(\d+) v=(-?\d+),(-?\d+)$")?;
-    let mut robots = input
+    let mut robots: Vec<Robot> = lines
+        .iter()
         .map(|line| {
-            let (_, [px, py, vx, vy]) = regex.captures(&line).unwrap().extract();
+            let [px, py, vx, vy] = line.int_array()?;
             Ok(Robot {
-                p: (px.parse()?, py.parse()?),
-                v: (vx.parse()?, vy.parse()?),
+                p: (px, py),
+                v: (vx, vy),
             })
         })
-        .map(Result::unwrap)
-        .collect_vec();
+        .try_collect()?;
 
     if c.part1 {
         let mut quadrants = [0; 4];
@@ -81,27 +91,32 @@ fn main() -> Result<()> {
         });
         println!("{}", quadrants.into_iter().product::<i32>());
     } else {
-        let mut grids: HashMap<Vec<u8>, i32> = HashMap::new();
-
-        for time in 1.. {
-            for robot in robots.iter_mut() {
-                robot.step(1);
-            }
-
-            println!("{time}");
+        // Every robot returns to where it started after lcm(WIDTH, HEIGHT) steps at most, so
+        // the sequence of grids repeats; look for the most orderly (most compressible) one.
+        let step = |robots: &Vec<Robot>| {
+            let mut robots = robots.clone();
+            robots.iter_mut().for_each(|r| r.step(1));
+            robots
+        };
+        let cycle = brent(robots.clone(), step);
+
+        let mut best: Option<(Vec<u8>, usize)> = None;
+        // Skip the starting grid, which is never the answer.
+        for time in 1..=cycle.start + cycle.length {
+            robots = step(&robots);
             let grid = stringify_grid(&robots);
             let mut encoder = GzEncoder::new(grid.as_bytes(), Compression::fast());
             let mut grid = Vec::new();
             encoder.read_to_end(&mut grid)?;
 
-            if let Entry::Vacant(e) = grids.entry(grid) {
-                e.insert(time);
-            } else {
-                break;
+            if best
+                .as_ref()
+                .is_none_or(|(best, _)| grid.len() < best.len())
+            {
+                best = Some((grid, time));
             }
         }
-
-        let (data, time) = grids.iter().min_by_key(|e| e.0.len()).unwrap();
+        let (data, time) = best.unwrap();
 
         let mut decoder = GzDecoder::new(data.as_slice());
         let mut grid = String::new();
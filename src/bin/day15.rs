@@ -12,18 +12,18 @@ enum Tile {
 
 fn main() -> Result<()> {
     let c = chal()?;
-    let mut input = c.input.lines().map(Result::unwrap);
+    let (grid, moves) = c.input.two_sections(Ok, Ok)?;
 
     let robot = Rc::new(RefCell::new(None));
 
     let mut grid = if c.part1 {
-        input
-            .by_ref()
-            .take_while(|line| !line.is_empty())
+        grid.lines
+            .into_iter()
             .enumerate()
             .map(|(y, line)| {
                 let robot = robot.clone();
-                line.into_bytes()
+                line.text
+                    .into_bytes()
                     .into_iter()
                     .enumerate()
                     .map(move |(x, b)| match b {
@@ -39,13 +39,13 @@ fn main() -> Result<()> {
             })
             .collect_2d()
     } else {
-        input
-            .by_ref()
-            .take_while(|line| !line.is_empty())
+        grid.lines
+            .into_iter()
             .enumerate()
             .map(|(y, line)| {
                 let robot = robot.clone();
-                line.into_bytes()
+                line.text
+                    .into_bytes()
                     .into_iter()
                     .enumerate()
                     .flat_map(move |(x, b)| match b {
@@ -70,7 +70,11 @@ fn main() -> Result<()> {
 
     let mut robot = robot.borrow().unwrap();
 
-    'fail: for b in input.flat_map(|line| line.into_bytes()) {
+    'fail: for b in moves
+        .lines
+        .into_iter()
+        .flat_map(|line| line.text.into_bytes())
+    {
         let direction = match b {
             b'^' => Direction::North,
             b'v' => Direction::South,
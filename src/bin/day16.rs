@@ -4,6 +4,7 @@ use std::{
 };
 
 pub use aoc_2024::prelude::*;
+use aoc_2024::random;
 
 enum Tile {
     Empty,
@@ -32,7 +33,26 @@ struct Visited {
 }
 
 fn main() -> Result<()> {
-    let c = chal()?;
+    let c = chal_gen(|rng, size, _, out| {
+        let mut maze = random::maze(rng, size, size);
+        let (w, h) = (maze[0].len(), maze.len());
+        // Knock out some walls between passages, so there are several routes to choose from.
+        for _ in 0..w * h / 20 {
+            let x = rng.range(1..w as i64 - 1) as usize;
+            let y = rng.range(1..h as i64 - 1) as usize;
+            let open = |x: usize, y: usize| maze[y][x] != b'#';
+            if (open(x - 1, y) && open(x + 1, y)) || (open(x, y - 1) && open(x, y + 1)) {
+                maze[y][x] = b'.';
+            }
+        }
+        maze[h - 2][1] = b'S';
+        maze[1][w - 2] = b'E';
+        for row in maze {
+            out.write_all(&row)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    })?;
     let mut maze = c
         .input
         .lines()
@@ -1,183 +1,90 @@
-use std::sync::Arc;
-
 pub use aoc_2024::prelude::*;
+use aoc_2024::vm::{Stop, Vm, analyze_loop, assemble, disassemble, symbolic};
 
-#[repr(u8)]
-#[derive(Clone, Copy)]
-enum Instruction {
-    Adv(Combo),
-    Bxl(u8),
-    Bst(Combo),
-    Jnz(u8),
-    Bxc,
-    Out(Combo),
-    Bdv(Combo),
-    Cdv(Combo),
-}
-
-#[derive(Clone, Copy)]
-struct Combo(u8);
-
-impl Combo {
-    fn apply(&self, vm: &Vm) -> u64 {
-        match self.0 {
-            n @ 0..4 => n as u64,
-            4 => vm.a,
-            5 => vm.b,
-            6 => vm.c,
-            _ => panic!("invalid operand"),
-        }
-    }
-}
+fn main() -> Result<()> {
+    let c = chal()?;
+    let lines: Vec<Line> = c.input.numbered_lines().try_collect()?;
 
-impl Instruction {
-    fn new(opcode: u8, operand: u8) -> Instruction {
-        use Instruction::*;
-        match opcode {
-            0 => Adv(Combo(operand)),
-            1 => Bxl(operand),
-            2 => Bst(Combo(operand)),
-            3 => Jnz(operand),
-            4 => Bxc,
-            5 => Out(Combo(operand)),
-            6 => Bdv(Combo(operand)),
-            7 => Cdv(Combo(operand)),
-            _ => panic!("invalid opcode"),
-        }
+    if c.options.flag("asm") {
+        // The input is assembly source rather than a puzzle input.
+        let program = assemble(&lines.iter().map(|line| &line.text).join("\n"))?;
+        println!("Program: {}", program.iter().join(","));
+        return Ok(());
     }
-}
 
-#[derive(Clone)]
-struct Vm {
-    a: u64,
-    b: u64,
-    c: u64,
-
-    pc: usize,
-    program: Arc<Vec<u8>>,
-}
-
-enum Status {
-    Step(Option<u64>),
-    Done,
-}
-
-impl Vm {
-    fn new(mut input: impl Iterator<Item = String>) -> Vm {
-        let a = input
-            .next()
-            .unwrap()
-            .strip_prefix("Register A: ")
-            .unwrap()
-            .parse()
-            .unwrap();
-        let b = input
-            .next()
-            .unwrap()
-            .strip_prefix("Register B: ")
-            .unwrap()
-            .parse()
-            .unwrap();
-        let c = input
-            .next()
-            .unwrap()
-            .strip_prefix("Register C: ")
-            .unwrap()
-            .parse()
-            .unwrap();
-
-        assert!(input.next().unwrap().is_empty());
-
-        let program = Arc::new(
-            input
-                .next()
-                .unwrap()
-                .strip_prefix("Program: ")
-                .unwrap()
-                .split(',')
-                .map(|x| x.parse::<u8>().unwrap())
-                .collect(),
-        );
+    let mut vm = Vm::parse(lines)?;
+    vm.max_steps = Some(match c.options.get("max-steps") {
+        Some(n) => n.parse().context("invalid --max-steps")?,
+        None => 1_000_000,
+    });
+    vm.detect_loops = true;
+    let program = vm.program.clone();
 
-        Vm {
-            a,
-            b,
-            c,
-            program,
-            pc: 0,
-        }
+    if c.options.flag("disassemble") {
+        print!("{}", disassemble(&program));
+        return Ok(());
     }
 
-    fn step(&mut self) -> Status {
-        let (Some(&opcode), Some(&operand)) =
-            (self.program.get(self.pc), self.program.get(self.pc + 1))
-        else {
-            return Status::Done;
-        };
-        let inst = Instruction::new(opcode, operand);
-
-        self.pc += 2;
-        match inst {
-            Instruction::Adv(combo) => self.a >>= combo.apply(self),
-            Instruction::Bdv(combo) => self.b = self.a >> combo.apply(self),
-            Instruction::Cdv(combo) => self.c = self.a >> combo.apply(self),
-
-            Instruction::Bxl(op) => self.b ^= op as u64,
-            Instruction::Bst(combo) => self.b = combo.apply(self) & 7,
-            Instruction::Jnz(op) => {
-                if self.a != 0 {
-                    self.pc = op as usize
-                }
-            }
-            Instruction::Bxc => self.b ^= self.c,
-            Instruction::Out(combo) => return Status::Step(Some(combo.apply(self) & 7)),
+    if c.part1 {
+        let mut vm = vm.clone();
+        vm.trace = c.options.flag("trace");
+        let (output, stop) = vm.run();
+        println!("{}", output.iter().join(","));
+        if !matches!(stop, Stop::Halted) {
+            bail!(stop);
         }
-
-        Status::Step(None)
+    } else if c.options.flag("symbolic") {
+        let program = vm.program.iter().map(|&n| n as u64).collect_vec();
+        let a = symbolic::invert(&vm, &program, vm.max_steps.unwrap() as usize)?
+            .context("no value of A produces a quine")?;
+        println!("{a}");
+    } else {
+        println!("{}", find_quine(&vm)?);
     }
-}
-
-impl Iterator for Vm {
-    type Item = u64;
 
-    fn next(&mut self) -> Option<u64> {
-        loop {
-            match self.step() {
-                Status::Step(None) => continue,
-                Status::Step(Some(x)) => return Some(x),
-                Status::Done => return None,
-            }
-        }
-    }
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let c = chal()?;
-    let input = c.input.lines().map(Result::unwrap);
-    let vm = Vm::new(input);
-    let program = vm.program.clone();
-
-    println!("{}", vm.clone().join(","));
-
-    let mut valid = (0..256).collect_vec();
-    for len in 0..program.len() {
-        valid = valid
+/// Finds the smallest value of register A which makes the program output itself.
+fn find_quine(vm: &Vm) -> Result<u64> {
+    let shape = analyze_loop(&vm.program).context("cannot search for a quine")?;
+    let bits = shape.bits_per_iteration;
+    let program = vm.program.iter().map(|&n| n as u64).collect_vec();
+
+    // The last iteration sees only the topmost bits of A, so build A up from the top: for each
+    // output from last to first, extend every candidate found so far by one iteration's worth of
+    // low bits, and keep those that produce the remainder of the program.
+    let mut candidates = vec![0u64];
+    for i in (0..program.len()).rev() {
+        candidates = candidates
             .into_iter()
-            .flat_map(|a| {
-                (0..8).map(move |x| {
-                    let shift = 8 + 3 * len;
-                    assert!(a >> shift == 0);
-                    a | (x << shift)
-                })
-            })
+            .flat_map(|a| (0..1 << bits).map(move |low| (a << bits) | low))
             .filter(|&a| {
-                Vm { a, ..vm.clone() }
-                    .take(len)
-                    .eq(program.iter().take(len).map(|n| *n as u64))
+                let mut candidate = Vm::with_program(a, vm.b, vm.c, vm.program.clone());
+                candidate.max_steps = vm.max_steps;
+                candidate.detect_loops = vm.detect_loops;
+                produces(&mut candidate, &program[i..])
             })
             .collect();
-        println!("{:?}", valid.first());
+        ensure!(
+            !candidates.is_empty(),
+            "no value of A produces the last {} outputs",
+            program.len() - i
+        );
     }
 
-    Ok(())
+    Ok(candidates.into_iter().min().unwrap())
+}
+
+/// Returns true if `vm` outputs exactly `expected` and then halts. Candidates which loop, run out
+/// of steps, or fault are rejected rather than waited on.
+fn produces(vm: &mut Vm, expected: &[u64]) -> bool {
+    let mut expected = expected.iter();
+    for output in vm.by_ref() {
+        match output {
+            Result::Ok(x) if expected.next() == Some(&x) => {}
+            _ => return false,
+        }
+    }
+    expected.next().is_none()
 }
@@ -1,14 +1,21 @@
+use aoc_2024::par;
 pub use aoc_2024::prelude::*;
 
 fn main() -> Result<()> {
     let c = chal()?;
-    let mut input = c.input.lines().map(Result::unwrap);
-
-    let towels = input.next().unwrap();
+    let (towels, designs) = c.input.two_sections(
+        |towels| {
+            towels.expect_lines(1)?;
+            Ok(towels.lines[0].text.clone())
+        },
+        |designs| Ok(designs.lines),
+    )?;
     let towels = towels.split(", ").collect_vec();
-    assert_eq!(input.next().as_deref(), Some(""));
 
-    let arrangements = input.map(|design| make_design(&design, 0, &mut Vec::new(), &towels));
+    let arrangements = par::map(&designs, |design| {
+        make_design(&design.text, 0, &mut Vec::new(), &towels)
+    })
+    .into_iter();
     if c.part1 {
         println!("{}", arrangements.filter(|&n| n != 0).count());
     } else {
@@ -2,34 +2,52 @@
 pub use aoc_2024::prelude::*;
 
 fn main() -> Result<()> {
-    let c = chal()?;
-    let input = c.input.lines().map(Result::unwrap);
-    let input = input.map(|line| {
-        line.split_whitespace()
-            .map(|w| w.parse::<u32>().expect("parse error"))
-            .collect::<Vec<_>>()
-    });
-    let safe = input.filter(|input| {
-        let mut skippable = if c.part2 {
-            0..=input.len()
-        } else {
-            input.len()..=input.len() // invalid index
-        };
+    let c = chal_gen(|rng, size, _, out| {
+        for _ in 0..size {
+            let mut level = rng.range(10..90);
+            let direction = if rng.chance(0.5) { 1 } else { -1 };
+            let mut levels = vec![level];
+            for _ in 0..rng.range(4..8) {
+                // Mostly safe steps, with the occasional bad one.
+                let step = if rng.chance(0.1) {
+                    *rng.choose(&[0, 4, 5, -1, -2])
+                } else {
+                    rng.range(1..4)
+                };
+                level = (level + direction * step).max(1);
+                levels.push(level);
+            }
+            writeln!(out, "{}", levels.iter().join(" "))?;
+        }
+        Ok(())
+    })?;
 
-        skippable.any(|i| {
-            let input = input
-                .iter()
-                .enumerate()
-                .filter_map(|(j, x)| if j == i { None } else { Some(x) });
+    let safe = c
+        .input
+        .par_sum_lines(|line| Ok(is_safe(&line.extract_ints::<u32>()?, c.part2) as u32))?;
+    println!("{safe}");
 
-            (input.clone().is_sorted() || input.clone().rev().is_sorted())
-                && input
-                    .map_windows(|&x| x)
-                    .all(|[&a, &b]| (1..=3).contains(&a.abs_diff(b)))
-        })
-    });
+    Ok(())
+}
 
-    println!("{}", safe.count());
+/// Returns true if the levels change steadily, allowing one level to be skipped if `dampener` is
+/// set.
+fn is_safe(levels: &[u32], dampener: bool) -> bool {
+    let mut skippable = if dampener {
+        0..=levels.len()
+    } else {
+        levels.len()..=levels.len() // invalid index
+    };
 
-    Ok(())
+    skippable.any(|i| {
+        let levels = levels
+            .iter()
+            .enumerate()
+            .filter_map(|(j, x)| if j == i { None } else { Some(x) });
+
+        (levels.clone().is_sorted() || levels.clone().rev().is_sorted())
+            && levels
+                .map_windows(|&x| x)
+                .all(|[&a, &b]| (1..=3).contains(&a.abs_diff(b)))
+    })
 }
@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 
 pub use aoc_2024::prelude::*;
+use aoc_2024::{Options, random, reference};
 
 #[derive(Copy, Clone)]
 enum Tile {
@@ -8,8 +9,57 @@ enum Tile {
     Wall,
 }
 
+/// The number of picoseconds a cheat must save to be counted, which can be lowered from the
+/// puzzle's 100 for small inputs.
+fn min_saving(options: &Options) -> Result<u16> {
+    match options.get("min-saving") {
+        Some(n) => n.parse().context("invalid --min-saving"),
+        None => Ok(100),
+    }
+}
+
 fn main() -> Result<()> {
-    let c = chal()?;
+    let c = chal_check(
+        |rng, size, _, out| {
+            // The race track is the path between two corners of a maze, with everything else
+            // walled off.
+            let maze = random::maze(rng, size, size);
+            let (w, h) = (maze[0].len(), maze.len());
+            let (start, end) = ((1, h - 2), (w - 2, 1));
+            let mut previous = HashMap::from([(start, start)]);
+            let mut queue = VecDeque::from([start]);
+            while let Some((x, y)) = queue.pop_front() {
+                for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if maze[ny][nx] == b'.' && !previous.contains_key(&(nx, ny)) {
+                        previous.insert((nx, ny), (x, y));
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+
+            let mut track = vec![vec![b'#'; w]; h];
+            let mut xy = end;
+            while xy != start {
+                track[xy.1][xy.0] = b'.';
+                xy = previous[&xy];
+            }
+            track[start.1][start.0] = b'S';
+            track[end.1][end.0] = b'E';
+            for row in track {
+                out.write_all(&row)?;
+                writeln!(out)?;
+            }
+            Ok(())
+        },
+        |input, part2, options| {
+            let cheat = if part2 { 20 } else { 2 };
+            let min_saving = min_saving(options)?;
+            Ok(Some(
+                reference::day20(input, cheat, min_saving as usize)?.to_string(),
+            ))
+        },
+    )?;
+    let min_saving = min_saving(&c.options)?;
 
     let mut maze = c
         .input
@@ -99,7 +149,7 @@ fn main() -> Result<()> {
                 (maze.get(start), maze.get(end))
             {
                 let d = d1 + d2 + distance_used as u16;
-                if d <= best_path.saturating_sub(100) {
+                if d <= best_path.saturating_sub(min_saving) {
                     num_cheats += 1;
                 }
             }
@@ -1,6 +1,7 @@
 use std::{cell::RefCell, iter::once, sync::LazyLock};
 
 pub use aoc_2024::prelude::*;
+use aoc_2024::reference;
 use ndarray::{Array2, array};
 
 struct Keypad<T> {
@@ -225,11 +226,26 @@ fn solve(input: &str, robots: usize) -> u64 {
 }
 
 fn main() -> Result<()> {
-    let c = chal()?;
-    let input = c.input.lines().map(Result::unwrap);
-
+    let c = chal_check(
+        |rng, size, _, out| {
+            for _ in 0..size {
+                writeln!(out, "{:03}A", rng.below(1000))?;
+            }
+            Ok(())
+        },
+        |input, part2, _| {
+            // Simulating every press is only feasible for a few robots.
+            Ok((!part2)
+                .then(|| reference::day21(input, 2))
+                .transpose()?
+                .map(|n| n.to_string()))
+        },
+    )?;
     let robots = if c.part1 { 2 } else { 25 };
-    println!("{}", input.map(|line| solve(&line, robots)).sum::<u64>());
+    let complexity = c
+        .input
+        .par_sum_lines(|line| Ok(solve(&line.text, robots)))?;
+    println!("{complexity}");
 
     Ok(())
 }
@@ -1,3 +1,4 @@
+use aoc_2024::par;
 pub use aoc_2024::prelude::*;
 
 fn rng(mut seed: u64) -> u64 {
@@ -7,40 +8,106 @@ fn rng(mut seed: u64) -> u64 {
     seed
 }
 
-fn main() -> Result<()> {
-    let c = chal()?;
+/// The number of possible windows of four price changes, each between -9 and 9.
+const WINDOWS: usize = 19usize.pow(4);
+
+/// How many buyers each thread handles at once.
+const SHARD_SIZE: usize = 256;
+
+/// How many buyers to read before starting on them, bounding memory use for large inputs.
+const BATCH_SIZE: usize = 1 << 14;
+
+/// Adds the price each buyer would sell at after each window of four price changes to `totals`,
+/// which is indexed by the encoded window.
+fn add_prices(seeds: &[u64], totals: &mut [u64]) {
+    // The stamp of the last buyer to see each window, so that only the first occurrence counts
+    // without having to clear anything between buyers.
+    let mut seen = vec![0u32; WINDOWS];
+    for (stamp, &seed) in (1..).zip(seeds) {
+        let mut secret = rng(seed);
+        let mut price = secret % 10;
+        let mut window = 0;
+        for i in 1..2000 {
+            secret = rng(secret);
+            let next = secret % 10;
+            window = (window * 19 + (next + 9 - price) as usize) % WINDOWS;
+            price = next;
+            if i >= 4 && seen[window] != stamp {
+                seen[window] = stamp;
+                totals[window] += price;
+            }
+        }
+    }
+}
 
-    let input = c.input.lines().map(Result::unwrap);
+/// Adds the prices for a batch of buyers to `totals`, sharding the buyers across threads.
+fn add_batch(seeds: &[u64], totals: &mut [u64]) {
+    let batch = par::fold_chunks(
+        seeds,
+        SHARD_SIZE,
+        || vec![0; WINDOWS],
+        |mut totals, shard| {
+            add_prices(shard, &mut totals);
+            totals
+        },
+        |mut totals, other| {
+            totals.iter_mut().zip(other).for_each(|(t, o)| *t += o);
+            totals
+        },
+    );
+    totals.iter_mut().zip(batch).for_each(|(t, b)| *t += b);
+}
+
+/// Decodes a window of price changes.
+fn changes(mut window: usize) -> [i32; 4] {
+    let mut changes = [0; 4];
+    for change in changes.iter_mut().rev() {
+        *change = (window % 19) as i32 - 9;
+        window /= 19;
+    }
+    changes
+}
 
-    let seeds = input.map(|line| line.parse::<u64>().unwrap());
+fn main() -> Result<()> {
+    let c = chal_gen(|rng, size, _, out| {
+        for _ in 0..size {
+            writeln!(out, "{}", rng.below(1 << 24))?;
+        }
+        Ok(())
+    })?;
 
     if c.part1 {
-        println!(
-            "{}",
-            seeds
-                .map(|seed| (0..2000).fold(seed, |seed, _| rng(seed)))
-                .sum::<u64>()
-        );
+        let sum = c.input.par_sum_lines(|line| {
+            let seed = line.parser().value::<u64>()?;
+            Ok((0..2000).fold(seed, |seed, _| rng(seed)))
+        })?;
+        println!("{sum}");
     } else {
-        let prices = seeds.map(|seed| {
-            (0..2000).scan(seed, |seed, _| {
-                *seed = rng(*seed);
-                Some(*seed % 10)
-            })
-        });
-
-        let mut results = HashMap::<[i32; 4], u64>::new();
-        for monkey in prices {
-            let mut my_results = HashMap::<[i32; 4], u64>::new();
-            for (a, b, c, d, e) in monkey.map(|i| i as i32).tuple_windows() {
-                let changes = [b - a, c - b, d - c, e - d];
-                my_results.entry(changes).or_insert(e as u64);
+        let mut totals = vec![0; WINDOWS];
+        let mut seeds = Vec::with_capacity(BATCH_SIZE);
+        c.input.for_each_line(|line| {
+            seeds.push(line.parser().value::<u64>()?);
+            if seeds.len() == BATCH_SIZE {
+                add_batch(&seeds, &mut totals);
+                seeds.clear();
             }
-            my_results
-                .into_iter()
-                .for_each(|(k, v)| *results.entry(k).or_default() += v);
-        }
-        println!("{:?}", results.iter().max_by_key(|(_, n)| *n).unwrap());
+            Ok(())
+        })?;
+        add_batch(&seeds, &mut totals);
+
+        // Break ties in favor of the first window, so the answer doesn't depend on scheduling.
+        let (window, best) = totals
+            .iter()
+            .enumerate()
+            .fold((0, 0), |best, (window, &total)| {
+                if total > best.1 {
+                    (window, total)
+                } else {
+                    best
+                }
+            });
+        println!("{best}");
+        println!("changes: {}", changes(window).iter().join(","));
     }
 
     Ok(())
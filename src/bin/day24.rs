@@ -1,45 +1,105 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, HashSet},
     fmt::{Debug, Display, Write},
 };
 
 pub use aoc_2024::prelude::*;
+use aoc_2024::{Options, random};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum Op {
     And,
     Or,
     Xor,
+    Nand,
+    Nor,
+    Xnor,
 }
 
 impl Op {
+    const ALL: [Op; 6] = [Op::And, Op::Or, Op::Xor, Op::Nand, Op::Nor, Op::Xnor];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Op::And => "AND",
+            Op::Or => "OR",
+            Op::Xor => "XOR",
+            Op::Nand => "NAND",
+            Op::Nor => "NOR",
+            Op::Xnor => "XNOR",
+        }
+    }
+
     fn apply(&self, in1: bool, in2: bool) -> bool {
         match self {
             Op::And => in1 & in2,
             Op::Or => in1 | in2,
             Op::Xor => in1 ^ in2,
+            Op::Nand => !(in1 & in2),
+            Op::Nor => !(in1 | in2),
+            Op::Xnor => !(in1 ^ in2),
+        }
+    }
+}
+
+/// The function a circuit is meant to compute from its x and y inputs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Target {
+    Add,
+    Subtract,
+    And,
+}
+
+impl Target {
+    fn apply(&self, x: u64, y: u64) -> u64 {
+        match self {
+            Target::Add => x.wrapping_add(y),
+            Target::Subtract => x.wrapping_sub(y),
+            Target::And => x & y,
         }
     }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+impl std::str::FromStr for Target {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Target> {
+        match s {
+            "add" => Ok(Target::Add),
+            "sub" | "subtract" => Ok(Target::Subtract),
+            "and" => Ok(Target::And),
+            _ => bail!("unknown target function '{s}' (expected add, sub or and)"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
 struct Label {
     chars: [u8; 3],
 }
 
 impl Label {
-    fn from_str(s: &str) -> Label {
-        assert_eq!(s.len(), 3);
-        Label {
-            chars: [s.as_bytes()[0], s.as_bytes()[1], s.as_bytes()[2]],
-        }
+    /// For a wire like `x12`, returns `(b'x', 12)`.
+    fn bit(&self) -> Option<(u8, u32)> {
+        let [prefix, tens @ b'0'..=b'9', ones @ b'0'..=b'9'] = self.chars else {
+            return None;
+        };
+        Some((prefix, (tens - b'0') as u32 * 10 + (ones - b'0') as u32))
+    }
+
+    fn parse(p: &mut Parser) -> Result<Label> {
+        let start = p.clone();
+        let s = p.take_while(|c| c.is_ascii_alphanumeric());
+        let chars = s.as_bytes().try_into().map_err(|_| {
+            start.error(format!("expected a three-character wire name, found '{s}'"))
+        })?;
+        Ok(Label { chars })
     }
 }
 
 impl Display for Label {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.chars
-            .iter().try_for_each(|c| f.write_char(*c as char))
+        self.chars.iter().try_for_each(|c| f.write_char(*c as char))
     }
 }
 
@@ -51,150 +111,779 @@ impl Debug for Label {
 
 #[derive(Debug, Copy, Clone)]
 enum Wire {
+    /// An input to the circuit.
     Value(bool),
+    Const(bool),
+    Not(Label),
     Gate(Op, Label, Label),
 }
 
 impl Wire {
-    fn parse_value(input: &str) -> (Label, Wire) {
-        let (gate, val) = input.split_once(": ").unwrap();
-        (
-            Label::from_str(gate),
-            Wire::Value(val.parse::<u8>().unwrap() != 0),
-        )
+    fn inputs(&self) -> impl Iterator<Item = Label> + use<> {
+        match *self {
+            Wire::Value(_) | Wire::Const(_) => [None, None],
+            Wire::Not(input) => [Some(input), None],
+            Wire::Gate(_, in1, in2) => [Some(in1), Some(in2)],
+        }
+        .into_iter()
+        .flatten()
     }
 
-    fn parse_gate(input: &str) -> (Label, Wire) {
-        let mut words = input.split(" ");
-        let in1 = words.next().unwrap();
-        let op = match words.next().unwrap() {
-            "AND" => Op::And,
-            "OR" => Op::Or,
-            "XOR" => Op::Xor,
-            _ => panic!("invalid input"),
+    /// The label of this wire's gate in a DOT graph, or None if it is an input.
+    fn gate_name(&self) -> Option<&'static str> {
+        match self {
+            Wire::Value(_) => None,
+            Wire::Const(false) => Some("0"),
+            Wire::Const(true) => Some("1"),
+            Wire::Not(_) => Some("NOT"),
+            Wire::Gate(op, _, _) => Some(op.name()),
+        }
+    }
+
+    /// Parses an input wire, like `x00: 1`.
+    fn parse_value(line: &Line) -> Result<(Label, Wire)> {
+        let mut p = line.parser();
+        let label = Label::parse(&mut p)?;
+        p.literal(": ")?;
+        let value = match p.int::<u8>()? {
+            0 => false,
+            1 => true,
+            n => bail!(line.error(p.column() - 1, format!("expected 0 or 1, found {n}"))),
         };
-        let in2 = words.next().unwrap();
-        assert_eq!(words.next().unwrap(), "->");
-        let out = words.next().unwrap();
+        p.finish()?;
+        Ok((label, Wire::Value(value)))
+    }
 
-        (
-            Label::from_str(out),
-            Wire::Gate(op, Label::from_str(in1), Label::from_str(in2)),
-        )
+    /// Parses a gate, like `x00 AND y00 -> z00`, `NOT x00 -> z00`, or `1 -> z00`.
+    fn parse_gate(line: &Line) -> Result<(Label, Wire)> {
+        let mut p = line.parser();
+        let start = p.clone();
+        let wire = match p.take_while(|c| c != ' ') {
+            "0" => Wire::Const(false),
+            "1" => Wire::Const(true),
+            "NOT" => {
+                p.literal(" ")?;
+                Wire::Not(Label::parse(&mut p)?)
+            }
+            _ => {
+                p = start;
+                let in1 = Label::parse(&mut p)?;
+                p.literal(" ")?;
+                let op_start = p.clone();
+                let op = p.take_while(|c| c != ' ');
+                let op = Op::ALL
+                    .into_iter()
+                    .find(|o| o.name() == op)
+                    .ok_or_else(|| op_start.error(format!("unknown gate '{op}'")))?;
+                p.literal(" ")?;
+                Wire::Gate(op, in1, Label::parse(&mut p)?)
+            }
+        };
+        p.literal(" -> ")?;
+        let out = Label::parse(&mut p)?;
+        p.finish()?;
+        Ok((out, wire))
     }
 }
 
-struct Wires { 
+/// A node in the DOT graph of a circuit: an input or output wire, or the gate driving a wire.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum DotNode {
+    Wire(Label),
+    Gate(Label, &'static str),
+}
+
+impl Display for DotNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DotNode::Wire(label) => write!(f, "{label}"),
+            DotNode::Gate(_, name) => f.write_str(name),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Wires {
     wires: BTreeMap<Label, Wire>,
-    swaps: Vec<[Label; 2]>
+    swaps: Vec<[Label; 2]>,
 }
 
 impl Wires {
-    fn read(mut input: impl Iterator<Item = String>) -> Wires {
-        let mut wires: BTreeMap<Label, Wire> = input
-            .by_ref()
-            .take_while(|line| !line.is_empty())
-            .map(|line| Wire::parse_value(&line))
-            .collect();
+    fn read(values: &Section, gates: &Section) -> Result<Wires> {
+        let mut wires = BTreeMap::new();
+        for line in &values.lines {
+            let (label, wire) = Wire::parse_value(line)?;
+            Self::check_bit(line, 1, label)?;
+            wires.insert(label, wire);
+        }
 
-        wires.extend(input.map(|line| Wire::parse_gate(&line)));
-        Wires { wires, swaps: Vec::new() }
+        for line in &gates.lines {
+            let (label, wire) = Wire::parse_gate(line)?;
+            let column = line.text.len() - 2;
+            Self::check_bit(line, column, label)?;
+            if wires.insert(label, wire).is_some() {
+                bail!(line.error(column, format!("wire {label} is driven more than once")));
+            }
+        }
+        Ok(Wires {
+            wires,
+            swaps: Vec::new(),
+        })
+    }
+
+    /// The simulator packs the x, y and z wires into 64-bit numbers, so rejects any wire (named
+    /// at `column` of `line`) beyond bit 63.
+    fn check_bit(line: &Line, column: usize, label: Label) -> Result<()> {
+        if let Some((b'x' | b'y' | b'z', bit)) = label.bit()
+            && bit >= 64
+        {
+            bail!(line.error(
+                column,
+                format!("wire {label} is beyond the 64 supported bits")
+            ));
+        }
+        Ok(())
     }
 
-    fn evaluate(&self, wire: Label) -> bool {
-        match &self.wires[&wire] {
-            Wire::Value(b) => *b,
-            Wire::Gate(op, in1, in2) => {
-                let op = *op;
-                op.apply(self.evaluate(*in1), self.evaluate(*in2))
+    /// Returns every wire in an order where each gate comes after both of its inputs, or an
+    /// error if the gates form a combinational loop.
+    fn topological_order(&self) -> Result<Vec<Label>> {
+        let mut graph = Graph::directed();
+        for (&label, wire) in &self.wires {
+            graph.intern(label);
+            for input in wire.inputs() {
+                ensure!(
+                    self.wires.contains_key(&input),
+                    "wire {input} (an input to {label}) is never driven"
+                );
+                graph.add_edge(input, label);
             }
         }
+
+        let labels = self.wires.keys().copied().collect_vec();
+        PartialOrder::from_graph(graph)
+            .sort(&labels)
+            .map_err(|e| match e {
+                SortError::Cycle(cycle) => {
+                    anyhow!(
+                        "combinational loop through wires {}",
+                        cycle.0.iter().join(", ")
+                    )
+                }
+                e => anyhow!(e),
+            })
+    }
+
+    /// Computes the value of every wire, visiting them in `order` (from `topological_order`). If
+    /// `inputs` is given, the x and y wires take their values from its bits instead of the netlist.
+    fn evaluate(&self, order: &[Label], inputs: Option<(u64, u64)>) -> HashMap<Label, bool> {
+        let mut values = HashMap::<Label, bool>::with_capacity(order.len());
+        for &label in order {
+            let value = match self.wires[&label] {
+                Wire::Value(b) => match (inputs, label.bit()) {
+                    (Some((x, _)), Some((b'x', bit))) => x >> bit & 1 != 0,
+                    (Some((_, y)), Some((b'y', bit))) => y >> bit & 1 != 0,
+                    _ => b,
+                },
+                Wire::Const(b) => b,
+                Wire::Not(input) => !values[&input],
+                Wire::Gate(op, in1, in2) => op.apply(values[&in1], values[&in2]),
+            };
+            values.insert(label, value);
+        }
+        values
+    }
+
+    /// Evaluates the circuit for the given x and y inputs, returning the z output.
+    fn simulate(&self, order: &[Label], x: u64, y: u64) -> u64 {
+        Self::number(&self.evaluate(order, Some((x, y))), b'z')
     }
 
-    fn find_gate(&mut self, op: Op, in1: Label, in2: Label) -> Label {
-        self.wires
+    /// Assembles the wires whose names start with `prefix` into a number, numbered by bit.
+    fn number(values: &HashMap<Label, bool>, prefix: u8) -> u64 {
+        values
             .iter()
-            .find(|(_, wire)| matches!(wire, Wire::Gate(o, i1, i2) if *o == op && ((*i1 == in1 && *i2 == in2) || (*i1 == in2 && *i2 == in1))))
-            .map(|(l, _)| *l)
-            .unwrap_or_else(|| {
-                for (label, &wire) in self.wires.iter() {
-                    if let 
-                        Wire::Gate(o, i1, i2) = wire {
-                            if o != op { continue; }
-                            let swaps = if i1 == in1 {
-                                Some([i2, in2])
-                            } else if i1 == in2 {
-                                Some([i2, in1])
-                            } else if i2 == in1 {
-                                Some([i1, in2])
-                            } else if i2 == in2 {
-                                Some([i1, in1])
-                            } else {
-                                None
-                            };
-                            if let Some(swap) = swaps {
-                                self.swaps.push(swap);
-                                return *label
-                            }
-                        }
-                    }
-                panic!("could not resolve swap");
-            })
+            .filter(|&(_, &value)| value)
+            .filter_map(|(label, _)| label.bit())
+            .filter(|&(p, _)| p == prefix)
+            .fold(0, |n, (_, bit)| n | 1 << bit)
     }
-}
 
-fn main() -> Result<()> {
-    let c = chal()?;
-    let input = c.input.lines().map(Result::unwrap);
-    let mut wires = Wires::read(input);
+    /// Writes the circuit as a Graphviz DOT graph. Gates and input/output wires are nodes, and
+    /// each edge is labeled with the wire it carries. Wires involved in a swap are drawn in red.
+    fn write_dot(&self, w: impl std::io::Write) -> std::io::Result<()> {
+        let node = |label: Label| match self.wires.get(&label).and_then(Wire::gate_name) {
+            Some(name) => DotNode::Gate(label, name),
+            None => DotNode::Wire(label),
+        };
+        let mut graph = Graph::directed();
+        for &label in self.wires.keys() {
+            let gate = graph.intern(node(label));
+            for input in self.wires[&label].inputs() {
+                let input = graph.intern(node(input));
+                graph.add_edge_ids(input, gate);
+            }
+            if label.chars[0] == b'z' {
+                graph.add_edge(node(label), DotNode::Wire(label));
+            }
+        }
+
+        let swapped = |label: Label| self.swaps.iter().flatten().any(|&l| l == label);
+        let color = |label: Label| if swapped(label) { "red" } else { "black" };
+        graph.write_dot_with(
+            w,
+            "rankdir=LR",
+            |id| match *graph.node(id) {
+                DotNode::Wire(label) if label.chars[0] == b'z' => {
+                    format!("shape=doublecircle, color={}", color(label))
+                }
+                DotNode::Wire(label) => format!("shape=circle, color={}", color(label)),
+                DotNode::Gate(label, _) => format!("shape=box, color={}", color(label)),
+            },
+            |a, b| match (*graph.node(a), *graph.node(b)) {
+                // A gate driving an output wire.
+                (_, DotNode::Wire(output)) => format!("color={}", color(output)),
+                (DotNode::Wire(input) | DotNode::Gate(input, _), _) => {
+                    let color = color(input);
+                    format!("label=\"{input}\", color={color}, fontcolor={color}")
+                }
+            },
+        )
+    }
 
-    let zs = wires.wires.keys().filter(|w| w.chars[0] == (b'z'));
-    let mut result: u64 = 0;
-    let mut mask = 1;
-    for z in zs {
-        if wires.evaluate(*z) {
-            result |= mask;
+    fn find_gate(&mut self, op: Op, in1: Label, in2: Label) -> Result<Label> {
+        let exact = self.wires.iter().find(|(_, wire)| {
+            matches!(wire, Wire::Gate(o, i1, i2)
+                if *o == op && ((*i1 == in1 && *i2 == in2) || (*i1 == in2 && *i2 == in1)))
+        });
+        if let Some((label, _)) = exact {
+            return Ok(*label);
         }
-        mask <<= 1;
+
+        for (label, &wire) in self.wires.iter() {
+            if let Wire::Gate(o, i1, i2) = wire {
+                if o != op {
+                    continue;
+                }
+                let swaps = if i1 == in1 {
+                    Some([i2, in2])
+                } else if i1 == in2 {
+                    Some([i2, in1])
+                } else if i2 == in1 {
+                    Some([i1, in2])
+                } else if i2 == in2 {
+                    Some([i1, in1])
+                } else {
+                    None
+                };
+                if let Some(swap) = swaps {
+                    self.swaps.push(swap);
+                    return Ok(*label);
+                }
+            }
+        }
+        bail!("could not resolve swap for {in1} {} {in2}", op.name());
+    }
+
+    /// Walks the circuit stage by stage, matching each gate of a ripple-carry adder and recording
+    /// a swap wherever a gate's inputs don't line up with the expected wires.
+    fn repair_structurally(&mut self) -> Result<()> {
+        let xs = self
+            .wires
+            .keys()
+            .filter(|w| w.chars[0] == (b'x'))
+            .copied()
+            .collect_vec();
+        let ys = self
+            .wires
+            .keys()
+            .filter(|w| w.chars[0] == (b'y'))
+            .copied()
+            .collect_vec();
+
+        let mut carry_in = None;
+        for (x, y) in xs.into_iter().zip(ys) {
+            let xor = self.find_gate(Op::Xor, x, y)?;
+            let (_result, carry_half0) = if let Some(carry) = carry_in {
+                (
+                    self.find_gate(Op::Xor, xor, carry)?,
+                    Some(self.find_gate(Op::And, xor, carry)?),
+                )
+            } else {
+                (xor, None)
+            };
+
+            let carry_half1 = self.find_gate(Op::And, x, y)?;
+            let carry_out = if carry_in.is_some() {
+                self.find_gate(Op::Or, carry_half0.unwrap(), carry_half1)?
+            } else {
+                carry_half1
+            };
+
+            carry_in = Some(carry_out);
+        }
+        Ok(())
+    }
+
+    fn input_bits(&self) -> u32 {
+        self.wires.keys().filter(|w| w.chars[0] == b'x').count() as u32
+    }
+
+    fn output_bits(&self) -> u32 {
+        self.wires.keys().filter(|w| w.chars[0] == b'z').count() as u32
+    }
+
+    /// Returns a mask of the output bits which differ from the `target` function for any of the
+    /// `tests`.
+    fn wrong_bits(&self, order: &[Label], tests: &[(u64, u64)], target: Target) -> u64 {
+        let mask = low_bits(self.output_bits());
+        tests.iter().fold(0, |wrong, &(x, y)| {
+            wrong | (self.simulate(order, x, y) ^ target.apply(x, y)) & mask
+        })
+    }
+
+    /// Returns true if output bits `0..=bit` are correct for all of the `tests`. Unlike
+    /// `wrong_bits`, this stops at the first failure.
+    fn correct_through(
+        &self,
+        order: &[Label],
+        tests: &[(u64, u64)],
+        target: Target,
+        bit: u32,
+    ) -> bool {
+        let mask = low_bits(bit + 1);
+        tests
+            .iter()
+            .all(|&(x, y)| (self.simulate(order, x, y) ^ target.apply(x, y)) & mask == 0)
+    }
+
+    /// Swaps the gates driving two wires.
+    fn swap(&mut self, a: Label, b: Label) {
+        let wire_a = self.wires[&a];
+        let wire_b = std::mem::replace(self.wires.get_mut(&b).unwrap(), wire_a);
+        self.wires.insert(a, wire_b);
     }
-    println!("{result}");
 
-    let xs = wires
-        .wires
-        .keys()
-        .filter(|w| w.chars[0] == (b'x'))
-        .copied()
-        .collect_vec();
-    let ys = wires
-        .wires
-        .keys()
-        .filter(|w| w.chars[0] == (b'y'))
-        .copied()
-        .collect_vec();
-
-    let mut carry_in = None;
-    for (x, y) in xs.into_iter().zip(ys.into_iter()) {
-        let xor = wires.find_gate(Op::Xor, x, y);
-        let (_result, carry_half0) = if let Some(carry) = carry_in {
-            (
-                wires.find_gate(Op::Xor, xor, carry),
-                Some(wires.find_gate(Op::And, xor, carry)),
-            )
+    /// After `a` and `b` have been swapped, fixes up `order` (a topological order from before the
+    /// swap, with each wire's index in `positions`) so that it is one again. Only the wires of
+    /// `a`, `b`, and anything between them in `order` can end up out of place, so only those are
+    /// re-sorted. Returns false if the swap created a combinational loop.
+    fn reorder_after_swap(
+        &self,
+        order: &mut [Label],
+        positions: &HashMap<Label, usize>,
+        a: Label,
+        b: Label,
+    ) -> bool {
+        let (i, j) = (positions[&a], positions[&b]);
+        let (lo, hi) = (i.min(j), i.max(j));
+        let segment = &mut order[lo..=hi];
+        let mut pending = vec![0; segment.len()];
+        let mut dependents = vec![Vec::new(); segment.len()];
+        for (i, &label) in segment.iter().enumerate() {
+            for input in self.wires[&label].inputs() {
+                if let Some(j) = positions[&input].checked_sub(lo)
+                    && j < segment.len()
+                {
+                    pending[i] += 1;
+                    dependents[j].push(i);
+                }
+            }
+        }
+
+        let mut ready = (0..segment.len())
+            .filter(|&i| pending[i] == 0)
+            .collect_vec();
+        let mut sorted = Vec::with_capacity(segment.len());
+        while let Some(i) = ready.pop() {
+            sorted.push(segment[i]);
+            for &d in &dependents[i] {
+                pending[d] -= 1;
+                if pending[d] == 0 {
+                    ready.push(d);
+                }
+            }
+        }
+        if sorted.len() < segment.len() {
+            return false;
+        }
+        segment.copy_from_slice(&sorted);
+        true
+    }
+
+    /// Searches for up to `max_swaps` pairs of gate outputs which, when swapped, make the circuit
+    /// compute `target` correctly on every one of the `tests`, given a topological `order` of the
+    /// circuit as it is. Returns None if there is no such set of swaps.
+    ///
+    /// To keep the search tractable, it always fixes the lowest incorrect output bit first: one
+    /// of the two swapped wires must belong to that bit's stage of the circuit (or the stage
+    /// before, which produces its carry), and the swap must move the lowest incorrect bit up.
+    fn repair_by_simulation(
+        &mut self,
+        order: &[Label],
+        tests: &[(u64, u64)],
+        target: Target,
+        max_swaps: usize,
+    ) -> Option<Vec<[Label; 2]>> {
+        let wrong = self.wrong_bits(order, tests, target);
+        if wrong == 0 {
+            return Some(Vec::new());
+        } else if max_swaps == 0 {
+            return None;
+        }
+        let lowest = wrong.trailing_zeros();
+
+        // Each wire's stage is the highest input bit it depends on.
+        let mut stages = HashMap::<Label, u32>::new();
+        for &label in order {
+            let wire = self.wires[&label];
+            let stage = match wire {
+                Wire::Value(_) => label.bit().map_or(0, |(_, bit)| bit),
+                _ => wire.inputs().map(|input| stages[&input]).max().unwrap_or(0),
+            };
+            stages.insert(label, stage);
+        }
+
+        let gates = order
+            .iter()
+            .copied()
+            .filter(|label| !matches!(self.wires[label], Wire::Value(_)))
+            .collect_vec();
+        let local = gates
+            .iter()
+            .copied()
+            .filter(|label| (lowest.saturating_sub(1)..=lowest).contains(&stages[label]))
+            .collect_vec();
+
+        let positions: HashMap<Label, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, &label)| (label, i))
+            .collect();
+        let mut swapped_order = order.to_vec();
+        for &a in &local {
+            for &b in &gates {
+                if a == b || (local.contains(&b) && b < a) {
+                    continue;
+                }
+                self.swap(a, b);
+                swapped_order.copy_from_slice(order);
+                let improved = self.reorder_after_swap(&mut swapped_order, &positions, a, b)
+                    && self.correct_through(&swapped_order, tests, target, lowest);
+                if improved
+                    && let Some(mut swaps) =
+                        self.repair_by_simulation(&swapped_order, tests, target, max_swaps - 1)
+                {
+                    self.swap(a, b);
+                    swaps.push([a, b]);
+                    return Some(swaps);
+                }
+                self.swap(a, b);
+            }
+        }
+        None
+    }
+}
+
+/// A mask of the lowest `n` bits, for any `n` up to 64.
+fn low_bits(n: u32) -> u64 {
+    u64::MAX.checked_shr(64 - n).unwrap_or(0)
+}
+
+/// Input pairs that exercise each bit of a `bits`-bit circuit individually, with and without
+/// carries, plus a handful of random pairs to catch interactions between stages.
+fn test_inputs(bits: u32) -> Vec<(u64, u64)> {
+    let mask = low_bits(bits);
+    let mut tests = vec![(0, 0), (mask, 0), (0, mask), (mask, 1), (1, mask)];
+    for bit in 0..bits {
+        let b = 1 << bit;
+        tests.extend([(b, 0), (0, b), (b, b), (mask, b), (b, mask)]);
+    }
+
+    let mut rng = random::Rng::new(0x2024_1224);
+    for _ in 0..64 {
+        tests.push((rng.next_u64() & mask, rng.next_u64() & mask));
+    }
+    tests
+}
+
+/// Writes a ripple-carry adder of `bits` bits with random inputs, as in the puzzle, with the
+/// outputs of `swaps` pairs of gates (each pair within a single stage) swapped. If `show_swaps`
+/// is set, the swapped wires are also printed to stderr.
+fn generate(
+    rng: &mut random::Rng,
+    bits: usize,
+    swaps: usize,
+    show_swaps: bool,
+    out: &mut dyn std::io::Write,
+) -> Result<()> {
+    ensure!(
+        swaps < bits,
+        "a {bits}-bit adder has room for at most {} swaps",
+        bits - 1
+    );
+    let mut names = HashSet::new();
+    let mut fresh = |rng: &mut random::Rng| loop {
+        let name: String = (0..3)
+            .map(|_| *rng.choose(b"abcdefghjkmnpqrstvw") as char)
+            .collect();
+        if names.insert(name.clone()) {
+            return name;
+        }
+    };
+
+    // Each stage after the first consists of five gates: sum = x ^ y, z = sum ^ carry_in,
+    // partial = sum & carry_in, both = x & y, and carry_out = partial | both.
+    let mut gates: Vec<[String; 4]> = Vec::new();
+    let mut carry = String::new();
+    for i in 0..bits {
+        let (x, y, z) = (format!("x{i:02}"), format!("y{i:02}"), format!("z{i:02}"));
+        if i == 0 {
+            carry = fresh(rng);
+            gates.push([x.clone(), "XOR".into(), y.clone(), z]);
+            gates.push([x, "AND".into(), y, carry.clone()]);
+            continue;
+        }
+        let (sum, partial, both) = (fresh(rng), fresh(rng), fresh(rng));
+        let carry_out = if i == bits - 1 {
+            format!("z{bits:02}")
         } else {
-            (xor, None)
+            fresh(rng)
         };
+        gates.push([x.clone(), "XOR".into(), y.clone(), sum.clone()]);
+        gates.push([sum.clone(), "XOR".into(), carry.clone(), z]);
+        gates.push([sum, "AND".into(), carry, partial.clone()]);
+        gates.push([x, "AND".into(), y, both.clone()]);
+        gates.push([partial, "OR".into(), both, carry_out.clone()]);
+        carry = carry_out;
+    }
+
+    // Swap the kinds of pairs that appear in the puzzle, none of which create a loop.
+    let mut stages = (1..bits).collect_vec();
+    rng.shuffle(&mut stages);
+    let mut swapped = Vec::new();
+    for &stage in &stages[..swaps] {
+        let base = 2 + 5 * (stage - 1);
+        let (a, b) = *rng.choose(&[(0, 3), (1, 2), (1, 4), (1, 3)]);
+        let (a, b) = (base + a, base + b);
+        let (out_a, out_b) = (gates[a][3].clone(), gates[b][3].clone());
+        gates[a][3] = out_b.clone();
+        gates[b][3] = out_a.clone();
+        swapped.extend([out_a, out_b]);
+    }
+    if show_swaps {
+        swapped.sort();
+        eprintln!("swapped: {}", swapped.join(","));
+    }
 
-        let carry_half1 = wires.find_gate(Op::And, x, y);
-        let carry_out = if carry_in.is_some() {
-            wires.find_gate(Op::Or, carry_half0.unwrap(), carry_half1)
+    for prefix in ['x', 'y'] {
+        for i in 0..bits {
+            writeln!(out, "{prefix}{i:02}: {}", rng.below(2))?;
+        }
+    }
+    writeln!(out)?;
+    rng.shuffle(&mut gates);
+    for [in1, op, in2, output] in gates {
+        let (in1, in2) = if rng.chance(0.5) {
+            (in1, in2)
         } else {
-            carry_half1
+            (in2, in1)
         };
+        writeln!(out, "{in1} {op} {in2} -> {output}")?;
+    }
+    Ok(())
+}
+
+/// Finds the pairs of swapped gate outputs which stop the circuit from computing its target
+/// function, recording them in `wires.swaps` and printing them.
+fn repair(wires: &mut Wires, order: &[Label], options: &Options) -> Result<()> {
+    let target = match options.get("target") {
+        Some(target) => target.parse()?,
+        None => Target::Add,
+    };
+    let structural = if target == Target::Add {
+        wires.repair_structurally()
+    } else {
+        Err(anyhow!(
+            "structural repair only supports ripple-carry adders; use --verify"
+        ))
+    };
+
+    if options.flag("verify") {
+        // Cross-check the structural repair against a brute-force search by simulation.
+        let tests = test_inputs(wires.input_bits());
+        let wrong = wires.wrong_bits(order, &tests, target);
+        println!(
+            "incorrect output bits: {}",
+            (0..64)
+                .filter(|bit| wrong >> bit & 1 != 0)
+                .map(|bit| format!("z{bit:02}"))
+                .join(",")
+        );
 
-        carry_in = Some(carry_out);
+        let max_swaps = match options.get("swaps") {
+            Some(n) => n.parse().context("invalid --swaps")?,
+            None => 4,
+        };
+        let mut simulated = wires.clone();
+        simulated.swaps.clear();
+        let Some(swaps) = simulated.repair_by_simulation(order, &tests, target, max_swaps) else {
+            bail!("no combination of up to {max_swaps} swaps fixes the circuit");
+        };
+        let simulated = swaps.iter().flatten().sorted().join(",");
+        println!("simulation: {simulated}");
+
+        match &structural {
+            Result::Ok(()) => {
+                let structural = wires.swaps.iter().flatten().sorted().dedup().join(",");
+                println!("structural: {structural}");
+                if structural == simulated {
+                    println!("results agree");
+                } else {
+                    println!("results differ!");
+                }
+            }
+            Err(e) => println!("structural: failed ({e})"),
+        }
+        if structural.is_err() {
+            wires.swaps = swaps;
+        }
+    } else {
+        structural?;
+    }
+
+    println!(
+        "{}",
+        wires.swaps.iter().flatten().sorted().dedup().join(",")
+    );
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let c = chal_gen(|rng, size, options, out| {
+        let swaps = match options.get("swaps") {
+            Some(n) => n.parse().context("invalid --swaps")?,
+            None => 4,
+        };
+        // The simulator works with 64-bit numbers, so the output (one bit wider than the
+        // inputs) must fit in 64 bits.
+        let show_swaps = options.flag("show-swaps");
+        generate(rng, size.clamp(2, 63), swaps, show_swaps, out)
+    })?;
+    let (values, gates) = c.input.two_sections(Ok, Ok)?;
+    let mut wires = Wires::read(&values, &gates)?;
+
+    let order = wires.topological_order()?;
+    let result = match (c.options.get("x"), c.options.get("y")) {
+        (None, None) => Wires::number(&wires.evaluate(&order, None), b'z'),
+        (x, y) => wires.simulate(
+            &order,
+            x.unwrap_or("0").parse().context("invalid --x")?,
+            y.unwrap_or("0").parse().context("invalid --y")?,
+        ),
+    };
+    println!("{result}");
+
+    if c.part2 {
+        repair(&mut wires, &order, &c.options)?;
+    }
+
+    if let Some(path) = c.options.get("dot") {
+        wires.write_dot(std::io::BufWriter::new(std::fs::File::create(path)?))?;
     }
-    
-    println!("{}", wires.swaps.into_iter().flatten().sorted().dedup().join(","));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use aoc_2024::parse::two_sections;
+
+    use super::*;
+
+    fn read(text: &str) -> Result<Wires> {
+        let source: Arc<str> = "test".into();
+        let lines = text.lines().enumerate().map(|(i, text)| Line {
+            number: i + 1,
+            text: text.to_owned(),
+            source: source.clone(),
+        });
+        let (values, gates) = two_sections(lines)?;
+        Wires::read(&values, &gates)
+    }
+
+    /// A circuit computing `x & y` bit by bit, with `bits` bits.
+    fn and_circuit(bits: u32) -> String {
+        let mut text = String::new();
+        for prefix in ['x', 'y'] {
+            for bit in 0..bits {
+                writeln!(text, "{prefix}{bit:02}: 1").unwrap();
+            }
+        }
+        writeln!(text).unwrap();
+        for bit in 0..bits {
+            writeln!(text, "x{bit:02} AND y{bit:02} -> z{bit:02}").unwrap();
+        }
+        text
+    }
+
+    #[test]
+    fn sixty_four_bits() {
+        let wires = read(&and_circuit(64)).unwrap();
+        assert_eq!((wires.input_bits(), wires.output_bits()), (64, 64));
+        let order = wires.topological_order().unwrap();
+        assert_eq!(Wires::number(&wires.evaluate(&order, None), b'z'), u64::MAX);
+        assert_eq!(wires.simulate(&order, u64::MAX, 1 << 63), 1 << 63);
+        let tests = test_inputs(wires.input_bits());
+        assert_eq!(wires.wrong_bits(&order, &tests, Target::And), 0);
+        assert_ne!(wires.wrong_bits(&order, &tests, Target::Add), 0);
+    }
+
+    #[test]
+    fn more_than_sixty_four_bits() {
+        let e = read(&and_circuit(65)).unwrap_err().to_string();
+        assert!(e.starts_with("test:65:1: wire x64 is beyond"), "{e}");
+
+        let text = and_circuit(64) + "x63 XOR y63 -> z64\n";
+        let e = read(&text).unwrap_err().to_string();
+        assert!(e.starts_with("test:194:16: wire z64 is beyond"), "{e}");
+    }
+
+    #[test]
+    fn reorder_after_swap() {
+        let mut wires = read(
+            "x00: 1\nx01: 0\ny00: 1\ny01: 1\n\n\
+             x00 AND y00 -> aaa\naaa OR x01 -> bbb\nbbb XOR y01 -> z00\nx01 AND y01 -> z01",
+        )
+        .unwrap();
+        let order = wires.topological_order().unwrap();
+        let positions = order.iter().enumerate().map(|(i, &l)| (l, i)).collect();
+        let label = |s: &str| Label {
+            chars: s.as_bytes().try_into().unwrap(),
+        };
+        let is_topological = |wires: &Wires, order: &[Label]| {
+            order.iter().enumerate().all(|(i, label)| {
+                wires.wires[label]
+                    .inputs()
+                    .all(|input| order[..i].contains(&input))
+            })
+        };
+
+        // Driving aaa from bbb's gate makes aaa depend on itself.
+        let (a, b) = (label("aaa"), label("bbb"));
+        wires.swap(a, b);
+        assert!(!wires.reorder_after_swap(&mut order.clone(), &positions, a, b));
+        wires.swap(a, b);
+
+        for (a, b) in [("aaa", "z01"), ("bbb", "z01"), ("z00", "z01")] {
+            let (a, b) = (label(a), label(b));
+            let mut swapped = order.clone();
+            wires.swap(a, b);
+            assert!(wires.reorder_after_swap(&mut swapped, &positions, a, b));
+            assert!(is_topological(&wires, &swapped), "{a} <-> {b}: {swapped:?}");
+            wires.swap(a, b);
+        }
+    }
+}
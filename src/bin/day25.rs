@@ -17,36 +17,40 @@ enum Input {
     Key(Key),
 }
 
-fn parse(mut input: impl Iterator<Item = String>) -> Input {
-    let key = input.next().unwrap() == ".....";
+fn parse(lines: &[Line]) -> Result<Input> {
+    let key = match lines[0].text.as_str() {
+        "....." => true,
+        "#####" => false,
+        _ => bail!(lines[0].error(1, "expected a row of '#' or '.' at the top of a schematic")),
+    };
     let mut result = [0, 0, 0, 0, 0];
-    for line in input {
-        for (j, c) in line.bytes().enumerate() {
-            result[j] += (c == b'#') as u8;
+    for line in &lines[1..] {
+        if line.text.len() != 5 {
+            bail!(line.error(1, "expected 5 columns"));
+        }
+        for (j, c) in line.text.bytes().enumerate() {
+            match c {
+                b'#' => result[j] += 1,
+                b'.' => {}
+                _ => bail!(line.error(j + 1, format!("unexpected '{}'", c as char))),
+            }
         }
     }
     if key {
         // keys have the extra row on the bottom
-        Input::Key(Key(result.map(|n| n - 1)))
+        Ok(Input::Key(Key(result.map(|n| n - 1))))
     } else {
-        Input::Lock(Lock(result))
+        Ok(Input::Lock(Lock(result)))
     }
 }
 
 fn main() -> Result<()> {
     let c = chal()?;
-    let input = c.input.lines().map(Result::unwrap);
-
     let mut locks = Vec::new();
     let mut keys = Vec::new();
 
-    for input in input
-        .chunk_by(|line| line.is_empty())
-        .into_iter()
-        .filter(|(empty, _)| !empty)
-        .map(|(_, group)| parse(group))
-    {
-        match input {
+    for section in c.input.sections()? {
+        match parse(&section.lines)? {
             Input::Lock(lock) => locks.push(lock),
             Input::Key(key) => keys.push(key),
         }
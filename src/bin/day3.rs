@@ -9,10 +9,7 @@ fn main() -> Result<()> {
 
     let mut idx = 0;
     let mut sum = 0;
-    loop {
-        let Some(next) = regex.captures_at(&input, idx) else {
-            break;
-        };
+    while let Some(next) = regex.captures_at(&input, idx) {
         idx = next.get(0).unwrap().range().end;
 
         if let (Some(a), Some(b)) = (next.get(1), next.get(2)) {
@@ -1,7 +1,10 @@
-pub use aoc_2024::prelude::*;
+use std::collections::HashSet;
+
+use aoc_2024::par;
+use aoc_2024::prelude::*;
 
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 enum Direction {
     North = 1,
     East = 2,
@@ -9,14 +12,36 @@ enum Direction {
     West = 8,
 }
 
+impl Direction {
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+            Direction::South => (0, 1),
+        }
+    }
+
+    fn turn(self) -> Direction {
+        match self {
+            Direction::North => Direction::East,
+            Direction::East => Direction::South,
+            Direction::South => Direction::West,
+            Direction::West => Direction::North,
+        }
+    }
+
+    fn index(self) -> usize {
+        (self as u8).trailing_zeros() as usize
+    }
+}
+
+#[derive(Clone)]
 struct DirectionSet(u8);
 impl DirectionSet {
     fn new() -> DirectionSet {
         DirectionSet(0)
     }
-    fn is_empty(&self) -> bool {
-        self.0 == 0
-    }
     fn contains(&self, dir: Direction) -> bool {
         self.0 & dir as u8 != 0
     }
@@ -35,7 +60,7 @@ struct Grid {
     tiles: Vec<Tile>,
     dim: usize,
 }
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct Guard {
     direction: Direction,
     position: (isize, isize),
@@ -54,6 +79,35 @@ impl Grid {
             }
         }
     }
+    fn position(&self, idx: usize) -> (isize, isize) {
+        ((idx % self.dim) as isize, (idx / self.dim) as isize)
+    }
+    /// Draws the grid with the tiles visited by `route` marked as `|`, `-`, or `+`.
+    fn render(&self, route: &Route) -> String {
+        let mut directions = vec![DirectionSet::new(); self.tiles.len()];
+        for guard in &route.steps {
+            let (x, y) = guard.position;
+            directions[self.idx(x, y).unwrap()].insert(guard.direction);
+        }
+
+        let mut s = String::new();
+        for (i, (tile, dirs)) in self.tiles.iter().zip(directions).enumerate() {
+            let horizontal = dirs.contains(Direction::East) || dirs.contains(Direction::West);
+            let vertical = dirs.contains(Direction::North) || dirs.contains(Direction::South);
+            s.push(match (tile.solid, horizontal, vertical) {
+                (true, _, _) => '#',
+                (false, true, true) => '+',
+                (false, true, false) => '-',
+                (false, false, true) => '|',
+                (false, false, false) => '.',
+            });
+            if i % self.dim == self.dim - 1 {
+                s.push('\n');
+            }
+        }
+        s
+    }
+
     fn get(&self, x: isize, y: isize) -> Option<&Tile> {
         Some(&self.tiles[self.idx(x, y)?])
     }
@@ -110,53 +164,143 @@ impl Grid {
 }
 
 impl Guard {
-    // Walks the grid, returning Ok if the guard leaves the maze or Err if he gets stuck in a loop.
-    fn walk(&self, grid: &mut Grid) -> Result<usize, usize> {
+    /// Walks the grid until the guard leaves it or gets stuck in a loop.
+    fn walk(&self, grid: &mut Grid) -> Route {
         let mut guard = self.clone();
 
         grid.tiles
             .iter_mut()
             .for_each(|tile| tile.visited = DirectionSet::new());
 
-        let mut unique = 0;
-        let mut loop_length = 0;
+        let mut route = Route {
+            steps: Vec::new(),
+            turns: Vec::new(),
+            loop_start: None,
+        };
         loop {
             let (x, y) = guard.position;
             let tile = grid.get_mut(x, y).unwrap();
-            if tile.visited.is_empty() {
-                unique += 1;
+            if !tile.visited.insert(guard.direction) {
+                route.loop_start = route.steps.iter().position(|g| *g == guard);
+                return route;
             }
+            route.steps.push(guard.clone());
 
-            if tile.visited.insert(guard.direction) {
-                loop_length += 1;
-            } else {
-                return Err(loop_length);
-            }
-
-            let (dx, dy) = match guard.direction {
-                Direction::North => (0, -1),
-                Direction::East => (1, 0),
-                Direction::West => (-1, 0),
-                Direction::South => (0, 1),
-            };
-
+            let (dx, dy) = guard.direction.delta();
             match grid.get(x + dx, y + dy) {
-                None => break,
+                None => return route,
                 Some(Tile { solid: true, .. }) => {
-                    guard.direction = match guard.direction {
-                        Direction::North => Direction::East,
-                        Direction::East => Direction::South,
-                        Direction::South => Direction::West,
-                        Direction::West => Direction::North,
-                    }
+                    route.turns.push(route.steps.len() - 1);
+                    guard.direction = guard.direction.turn();
                 }
                 Some(Tile { solid: false, .. }) => {
                     guard.position = (x + dx, y + dy);
                 }
             }
         }
+    }
+
+    /// Returns true if the guard gets stuck in a loop after an obstacle is placed at `obstacle`,
+    /// jumping from one turn to the next using `jumps`.
+    fn loops_with(&self, grid: &Grid, jumps: &JumpTable, obstacle: (isize, isize)) -> bool {
+        let mut guard = self.clone();
+        let mut turns = HashSet::new();
+        loop {
+            let (x, y) = guard.position;
+            let (dx, dy) = guard.direction.delta();
+            let stop = jumps.stops[grid.idx(x, y).unwrap()][guard.direction.index()];
+
+            // The new obstacle is in the way if it's ahead of the guard, on the same line, and no
+            // further than the existing obstacle.
+            let distance = |(px, py): (isize, isize)| (px - x) * dx + (py - y) * dy;
+            let in_line = (obstacle.0 - x) * dy == 0 && (obstacle.1 - y) * dx == 0;
+            let blocked = in_line
+                && distance(obstacle) > 0
+                && stop.is_none_or(|stop| distance(obstacle) <= distance(grid.position(stop)));
+
+            guard.position = if blocked {
+                (obstacle.0 - dx, obstacle.1 - dy)
+            } else {
+                match stop {
+                    Some(stop) => grid.position(stop),
+                    None => return false,
+                }
+            };
+            guard.direction = guard.direction.turn();
+            if !turns.insert((guard.position, guard.direction.index())) {
+                return true;
+            }
+        }
+    }
+}
+
+/// The path a guard takes through the grid.
+struct Route {
+    /// Every state the guard passes through, in order. Turning in place is a separate step.
+    steps: Vec<Guard>,
+    /// The indices of the steps at which the guard turns.
+    turns: Vec<usize>,
+    /// If the guard gets stuck in a loop, the index of the first step that is part of it.
+    loop_start: Option<usize>,
+}
+
+impl Route {
+    /// The repeating part of the route, or None if the guard leaves the grid.
+    fn cycle(&self) -> Option<&[Guard]> {
+        Some(&self.steps[self.loop_start?..])
+    }
 
-        Result::Ok(unique)
+    fn unique_tiles(&self) -> usize {
+        self.steps.iter().map(|g| g.position).unique().count()
+    }
+
+    /// The guard's state just before entering each tile for the first time (excluding the
+    /// starting tile).
+    fn first_entries(&self) -> Vec<Guard> {
+        let mut entered = HashSet::new();
+        entered.insert(self.steps[0].position);
+        self.steps
+            .iter()
+            .tuple_windows()
+            .filter(|(_, next)| entered.insert(next.position))
+            .map(|(prev, _)| prev.clone())
+            .collect()
+    }
+}
+
+/// For each tile and direction, the tile where a guard walking in that direction would stop
+/// because the next tile is an obstacle, or None if the guard would leave the grid.
+struct JumpTable {
+    stops: Vec<[Option<usize>; 4]>,
+}
+
+impl JumpTable {
+    fn new(grid: &Grid) -> JumpTable {
+        let mut stops = vec![[None; 4]; grid.tiles.len()];
+        for direction in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let (dx, dy) = direction.delta();
+            // Visit tiles in an order such that the next tile in `direction` has already been
+            // visited.
+            let order: Box<dyn Iterator<Item = usize>> = if dx + dy < 0 {
+                Box::new(0..grid.tiles.len())
+            } else {
+                Box::new((0..grid.tiles.len()).rev())
+            };
+            for i in order {
+                let (x, y) = grid.position(i);
+                stops[i][direction.index()] = match grid.idx(x + dx, y + dy) {
+                    None => None,
+                    Some(next) if grid.tiles[next].solid => Some(i),
+                    Some(next) => stops[next][direction.index()],
+                };
+            }
+        }
+        JumpTable { stops }
     }
 }
 
@@ -174,22 +318,44 @@ fn main() -> Result<()> {
     }
 
     let guard = guard.expect("no guard");
+    let route = guard.walk(&mut grid);
+    if c.options.flag("render") {
+        print!("{}", grid.render(&route));
+    }
+    ensure!(route.cycle().is_none(), "the guard never leaves the grid");
+
     if !c.part2 {
-        println!("{}", guard.walk(&mut grid).expect("loop"));
-    } else {
-        let mut loops = 0;
-        for i in 0..grid.tiles.len() {
-            if grid.tiles[i].solid {
-                continue;
-            }
+        println!("{}", route.unique_tiles());
+    } else if let Some(length) = c.options.get("loop-length") {
+        // List the obstacle placements that trap the guard in a loop of the given length.
+        let length: usize = length.parse().context("invalid --loop-length")?;
+        let mut count = 0;
+        for entry in route.first_entries() {
+            let (x, y) = entry.position;
+            let (dx, dy) = entry.direction.delta();
+            let obstacle = grid.idx(x + dx, y + dy).unwrap();
 
-            grid.tiles[i].solid = true;
-            if guard.walk(&mut grid).is_err() {
-                loops += 1;
+            grid.tiles[obstacle].solid = true;
+            let route = guard.walk(&mut grid);
+            grid.tiles[obstacle].solid = false;
+            if route.cycle().is_some_and(|cycle| cycle.len() == length) {
+                println!("{},{}", x + dx, y + dy);
+                count += 1;
             }
-            grid.tiles[i].solid = false;
         }
-        println!("{loops}");
+        println!("{count}");
+    } else {
+        // Only an obstacle on the original route can change it. The route is the same up to
+        // the first time the guard would have entered the obstacle's tile, so resume from there.
+        let jumps = JumpTable::new(&grid);
+        let entries = route.first_entries();
+        let loops = |entry: &Guard| {
+            let (x, y) = entry.position;
+            let (dx, dy) = entry.direction.delta();
+            entry.loops_with(&grid, &jumps, (x + dx, y + dy))
+        };
+        let loops = par::map(&entries, loops);
+        println!("{}", loops.into_iter().filter(|&l| l).count());
     }
 
     Ok(())
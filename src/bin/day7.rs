@@ -1,33 +1,44 @@
 use aoc_2024::prelude::*;
 
 fn main() -> Result<()> {
-    let c = chal()?;
-    let input = c.input.lines().map(Result::unwrap);
+    let c = chal_gen(|rng, size, _, out| {
+        let ops = [Operator::Add, Operator::Mul, Operator::Concat];
+        for _ in 0..size {
+            let nums = (0..rng.range(2..7))
+                .map(|_| rng.range(1..100) as u64)
+                .collect_vec();
+            let mut result = nums[1..].iter().fold(nums[0], |accum, &n| {
+                rng.choose(&ops)
+                    .apply(accum, n)
+                    .unwrap_or_else(|| accum.saturating_add(n))
+            });
+            // Make some equations unsolvable (most likely).
+            if rng.chance(0.5) {
+                result += rng.range(1..100) as u64;
+            }
+            writeln!(out, "{result}: {}", nums.iter().join(" "))?;
+        }
+        Ok(())
+    })?;
 
-    let ok = input
-        .map(|line| {
-            let (first, rest) = line.split_once(": ").unwrap();
-            (
-                first.parse::<u64>().unwrap(),
-                rest.split(' ')
-                    .map(|n| n.parse::<u64>().unwrap())
-                    .collect::<Vec<u64>>(),
-            )
+    let ops: &[Operator] = if c.part2 {
+        &[Operator::Add, Operator::Mul, Operator::Concat]
+    } else {
+        &[Operator::Add, Operator::Mul]
+    };
+    let total = c.input.par_sum_lines(|line| {
+        let nums = line.extract_ints::<u64>()?;
+        ensure!(
+            nums.len() >= 2,
+            line.error(1, "expected a result and operands")
+        );
+        Ok(if check(ops, nums[0], nums[1], &nums[2..]) {
+            nums[0]
+        } else {
+            0
         })
-        .filter(|(result, nums)| {
-            check(
-                if c.part2 {
-                    &[Operator::Add, Operator::Mul, Operator::Concat]
-                } else {
-                    &[Operator::Add, Operator::Mul]
-                },
-                *result,
-                nums[0],
-                &nums[1..],
-            )
-        });
-
-    println!("{}", ok.map(|(result, _)| result).sum::<u64>());
+    })?;
+    println!("{total}");
 
     Ok(())
 }
@@ -53,10 +64,11 @@ fn check(ops: &[Operator], target: u64, accum: u64, nums: &[u64]) -> bool {
         target == accum
     } else {
         for op in ops {
-            if let Some(accum) = op.apply(accum, nums[0]) {
-                if accum <= target && check(ops, target, accum, &nums[1..]) {
-                    return true;
-                }
+            if let Some(accum) = op.apply(accum, nums[0])
+                && accum <= target
+                && check(ops, target, accum, &nums[1..])
+            {
+                return true;
             }
         }
         false
@@ -56,7 +56,7 @@ fn main() -> Result<()> {
         for x in 0..width {
             if let Some((c, _)) = frequencies
                 .iter()
-                .find(|(_, antennas)| antennas.iter().any(|&a| (x, y) == a))
+                .find(|(_, antennas)| antennas.contains(&(x, y)))
             {
                 print!("{}", *c as char);
             } else if antinode(x, y) {
@@ -1,7 +1,14 @@
 pub use aoc_2024::prelude::*;
 
 fn main() -> Result<()> {
-    let c = chal()?;
+    let c = chal_gen(|rng, size, _, out| {
+        // Alternating file and free space lengths; files are never empty.
+        for i in 0..size {
+            write!(out, "{}", rng.range(if i % 2 == 0 { 1 } else { 0 }..10))?;
+        }
+        writeln!(out)?;
+        Ok(())
+    })?;
     let input = c.input.lines().next().unwrap()?;
 
     let files = input
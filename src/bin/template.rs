@@ -2,6 +2,7 @@ pub use aoc_2024::prelude::*;
 
 fn main() -> Result<()> {
     let c = chal()?;
+    #[allow(unused)]
     let input = c.input.lines().map(Result::unwrap);
 
     Ok(())
@@ -0,0 +1,153 @@
+//! Cycle detection for sequences of states produced by repeatedly applying a function.
+
+use std::{collections::HashMap, hash::Hash};
+
+/// The repeating part of a sequence `x0, x1, x2, ...`: every state from step `start` onwards is
+/// equal to the state `length` steps later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Returns the earliest step whose state is the same as the state at step `n`.
+    pub fn reduce(&self, n: u64) -> usize {
+        let start = self.start as u64;
+        if n < start {
+            n as usize
+        } else {
+            self.start + ((n - start) % self.length as u64) as usize
+        }
+    }
+}
+
+/// Finds the cycle in the sequence starting at `initial` and continuing with `step`, using
+/// Brent's algorithm. Only a few states are held at once, but `step` is called roughly three
+/// times as often as the length of the sequence up to the end of the first cycle.
+///
+/// The sequence must eventually repeat, or this never returns.
+pub fn brent<T: Clone + PartialEq>(initial: T, mut step: impl FnMut(&T) -> T) -> Cycle {
+    // Find the cycle length by racing a hare against a tortoise which teleports to the hare
+    // every power of two steps.
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(&initial);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    // Then find the start by walking two pointers `length` steps apart until they meet.
+    let mut tortoise = initial.clone();
+    let mut hare = initial;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+    let mut start = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    Cycle { start, length }
+}
+
+/// Finds the cycle in a sequence of states by remembering every state seen, stopping as soon as
+/// one repeats. Returns None if the sequence ends first.
+pub fn find_cycle<T: Hash + Eq>(states: impl IntoIterator<Item = T>) -> Option<Cycle> {
+    let mut seen = HashMap::new();
+    for (i, state) in states.into_iter().enumerate() {
+        if let Some(start) = seen.insert(state, i) {
+            return Some(Cycle {
+                start,
+                length: i - start,
+            });
+        }
+    }
+    None
+}
+
+/// Computes the state at step `n` of the sequence starting at `initial` and continuing with
+/// `step`, for `n` far beyond the point where the sequence starts repeating.
+pub fn state_at<T: Clone + PartialEq>(initial: T, mut step: impl FnMut(&T) -> T, n: u64) -> T {
+    let cycle = brent(initial.clone(), &mut step);
+    let mut state = initial;
+    for _ in 0..cycle.reduce(n) {
+        state = step(&state);
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A step function for the sequence `0, 1, 2, ...` that jumps back to `start` after
+    /// `start + length - 1`, so it has exactly that cycle.
+    fn rho(start: usize, length: usize) -> impl Fn(&usize) -> usize {
+        move |&x| if x + 1 < start + length { x + 1 } else { start }
+    }
+
+    const SHAPES: [(usize, usize); 7] = [(0, 1), (0, 5), (1, 1), (3, 1), (3, 4), (10, 7), (7, 64)];
+
+    #[test]
+    fn brent_finds_known_cycles() {
+        for (start, length) in SHAPES {
+            assert_eq!(brent(0, rho(start, length)), Cycle { start, length });
+        }
+    }
+
+    #[test]
+    fn find_cycle_finds_known_cycles() {
+        for (start, length) in SHAPES {
+            let states = std::iter::successors(Some(0), |x| Some(rho(start, length)(x)));
+            assert_eq!(find_cycle(states), Some(Cycle { start, length }));
+        }
+        assert_eq!(find_cycle(0..100), None);
+    }
+
+    #[test]
+    fn state_at_matches_stepping() {
+        for (start, length) in SHAPES {
+            let step = rho(start, length);
+            let mut state = 0;
+            // Including steps before the cycle starts, where `reduce` must leave `n` alone.
+            for n in 0..3 * (start + length) as u64 {
+                assert_eq!(
+                    state_at(0, &step, n),
+                    state,
+                    "step {n} of ({start}, {length})"
+                );
+                state = step(&state);
+            }
+        }
+    }
+
+    #[test]
+    fn reduce() {
+        let cycle = Cycle {
+            start: 3,
+            length: 4,
+        };
+        assert_eq!(
+            [0, 1, 2, 3, 6, 7, 8].map(|n| cycle.reduce(n)),
+            [0, 1, 2, 3, 6, 3, 4]
+        );
+        assert_eq!(cycle.reduce(1_000_000_000_000_003), 3);
+
+        // An already periodic sequence.
+        let cycle = Cycle {
+            start: 0,
+            length: 5,
+        };
+        assert_eq!([0, 4, 5, 13].map(|n| cycle.reduce(n)), [0, 4, 0, 3]);
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    borrow::Borrow,
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap, HashMap},
+    fmt::Display,
+    hash::Hash,
+    str::FromStr,
+};
+
+use anyhow::Result;
+use itertools::Itertools;
+
+use crate::parse::{Line, LineContext};
+
+pub type NodeId = usize;
+
+/// A directed or undirected graph whose nodes are interned into dense `NodeId`s.
+#[derive(Clone, Debug)]
+pub struct Graph<N> {
+    directed: bool,
+    nodes: Vec<N>,
+    ids: HashMap<N, NodeId>,
+    successors: Vec<BTreeSet<NodeId>>,
+    // Only populated for directed graphs; undirected graphs use `successors` for both.
+    predecessors: Vec<BTreeSet<NodeId>>,
+}
+
+impl<N: Hash + Eq + Clone> Graph<N> {
+    pub fn directed() -> Self {
+        Self::new(true)
+    }
+
+    pub fn undirected() -> Self {
+        Self::new(false)
+    }
+
+    fn new(directed: bool) -> Self {
+        Graph {
+            directed,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+        }
+    }
+
+    /// Parses a graph from lines of the form `a<sep>b`, one edge per line. Undirected graphs
+    /// may not have self-loops.
+    pub fn parse_edges(directed: bool, lines: &[Line], separator: &str) -> Result<Self>
+    where
+        N: FromStr<Err: Display>,
+    {
+        let mut graph = Self::new(directed);
+        for line in lines {
+            let Some((a, b)) = line.text.split_once(separator) else {
+                return Err(line.error(1, format!("expected '{separator}'")));
+            };
+            let b_column = a.chars().count() + separator.chars().count() + 1;
+            let a = a
+                .parse()
+                .map_err(|e| format!("invalid node '{a}': {e}"))
+                .at(line, 1)?;
+            let b = b
+                .parse()
+                .map_err(|e| format!("invalid node '{b}': {e}"))
+                .at(line, b_column)?;
+            if !directed && a == b {
+                return Err(line.error(1, "an undirected edge must join two different nodes"));
+            }
+            graph.add_edge(a, b);
+        }
+        Ok(graph)
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges().count()
+    }
+
+    /// Returns the ID of a node, adding it to the graph if it is not already present.
+    pub fn intern(&mut self, node: N) -> NodeId {
+        if let Some(&id) = self.ids.get(&node) {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(node.clone());
+        self.ids.insert(node, id);
+        self.successors.push(BTreeSet::new());
+        if self.directed {
+            self.predecessors.push(BTreeSet::new());
+        }
+        id
+    }
+
+    pub fn id<Q: Hash + Eq + ?Sized>(&self, node: &Q) -> Option<NodeId>
+    where
+        N: Borrow<Q>,
+    {
+        self.ids.get(node).copied()
+    }
+
+    pub fn node(&self, id: NodeId) -> &N {
+        &self.nodes[id]
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = NodeId> + use<N> {
+        0..self.nodes.len()
+    }
+
+    pub fn add_edge(&mut self, a: N, b: N) -> (NodeId, NodeId) {
+        let (a, b) = (self.intern(a), self.intern(b));
+        self.add_edge_ids(a, b);
+        (a, b)
+    }
+
+    /// Adds an edge between two nodes. Self-loops are ignored in undirected graphs, where they
+    /// would make a node its own neighbor.
+    pub fn add_edge_ids(&mut self, a: NodeId, b: NodeId) {
+        if !self.directed && a == b {
+            return;
+        }
+        self.successors[a].insert(b);
+        if self.directed {
+            self.predecessors[b].insert(a);
+        } else {
+            self.successors[b].insert(a);
+        }
+    }
+
+    pub fn has_edge(&self, a: NodeId, b: NodeId) -> bool {
+        self.successors[a].contains(&b)
+    }
+
+    /// Iterates over the nodes reachable from `id` by a single edge.
+    pub fn neighbors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.successors[id].iter().copied()
+    }
+
+    /// Iterates over the nodes with an edge leading to `id`.
+    pub fn predecessors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        if self.directed {
+            &self.predecessors[id]
+        } else {
+            &self.successors[id]
+        }
+        .iter()
+        .copied()
+    }
+
+    /// The number of outgoing edges (or all edges, for an undirected graph).
+    pub fn degree(&self, id: NodeId) -> usize {
+        self.successors[id].len()
+    }
+
+    pub fn in_degree(&self, id: NodeId) -> usize {
+        if self.directed {
+            self.predecessors[id].len()
+        } else {
+            self.successors[id].len()
+        }
+    }
+
+    /// Iterates over all edges. Each edge of an undirected graph is returned once, lowest ID first.
+    pub fn edges(&self) -> impl Iterator<Item = (NodeId, NodeId)> + '_ {
+        self.successors.iter().enumerate().flat_map(move |(a, bs)| {
+            bs.iter()
+                .copied()
+                .filter(move |&b| self.directed || a <= b)
+                .map(move |b| (a, b))
+        })
+    }
+
+    /// Builds the subgraph consisting of the given nodes and all edges between them.
+    /// Node IDs are not preserved.
+    pub fn induced_subgraph(&self, ids: impl IntoIterator<Item = NodeId>) -> Graph<N> {
+        let mut subgraph = Self::new(self.directed);
+        let mut mapping = HashMap::new();
+        for id in ids {
+            mapping.insert(id, subgraph.intern(self.nodes[id].clone()));
+        }
+        for (&old, &new) in &mapping {
+            for b in self.neighbors(old) {
+                if let Some(&b) = mapping.get(&b) {
+                    subgraph.add_edge_ids(new, b);
+                }
+            }
+        }
+        subgraph
+    }
+
+    /// Orders the nodes by repeatedly removing a node of minimum degree. Following this order, each
+    /// node has at most *d* neighbors later in the order, where *d* is the graph's degeneracy.
+    pub fn degeneracy_ordering(&self) -> Vec<NodeId> {
+        let mut degree = self.nodes().map(|id| self.degree(id)).collect_vec();
+        let mut removed = vec![false; self.len()];
+        let mut queue: BinaryHeap<_> = self.nodes().map(|id| Reverse((degree[id], id))).collect();
+        let mut ordering = Vec::with_capacity(self.len());
+
+        while let Some(Reverse((d, id))) = queue.pop() {
+            if removed[id] || d != degree[id] {
+                continue; // stale entry
+            }
+            removed[id] = true;
+            ordering.push(id);
+            for n in self.neighbors(id) {
+                if !removed[n] {
+                    degree[n] -= 1;
+                    queue.push(Reverse((degree[n], n)));
+                }
+            }
+        }
+        ordering
+    }
+
+    /// Calls `f` with every maximal clique of an undirected graph, using the Bron–Kerbosch
+    /// algorithm with pivoting over a degeneracy ordering.
+    pub fn for_each_maximal_clique(&self, mut f: impl FnMut(&[NodeId])) {
+        assert!(!self.directed, "cliques require an undirected graph");
+
+        let ordering = self.degeneracy_ordering();
+        let mut position = vec![0; self.len()];
+        for (i, &id) in ordering.iter().enumerate() {
+            position[id] = i;
+        }
+
+        let mut clique = Vec::new();
+        for &v in &ordering {
+            let (later, earlier) = self.neighbors(v).partition(|&n| position[n] > position[v]);
+            clique.push(v);
+            self.bron_kerbosch(&mut clique, later, earlier, &mut f);
+            clique.pop();
+        }
+    }
+
+    fn bron_kerbosch(
+        &self,
+        clique: &mut Vec<NodeId>,
+        mut candidates: BTreeSet<NodeId>,
+        mut excluded: BTreeSet<NodeId>,
+        f: &mut impl FnMut(&[NodeId]),
+    ) {
+        // Choose the pivot that leaves us with the fewest branches to explore.
+        let Some(pivot) = candidates
+            .iter()
+            .chain(&excluded)
+            .copied()
+            .max_by_key(|&u| candidates.iter().filter(|&&v| self.has_edge(u, v)).count())
+        else {
+            f(clique);
+            return;
+        };
+
+        let branches = candidates
+            .iter()
+            .copied()
+            .filter(|&v| !self.has_edge(pivot, v))
+            .collect_vec();
+        for v in branches {
+            let neighbors = &self.successors[v];
+            clique.push(v);
+            self.bron_kerbosch(
+                clique,
+                candidates.intersection(neighbors).copied().collect(),
+                excluded.intersection(neighbors).copied().collect(),
+                f,
+            );
+            clique.pop();
+            candidates.remove(&v);
+            excluded.insert(v);
+        }
+    }
+
+    pub fn maximal_cliques(&self) -> Vec<Vec<NodeId>> {
+        let mut cliques = Vec::new();
+        self.for_each_maximal_clique(|clique| cliques.push(clique.to_vec()));
+        cliques
+    }
+
+    pub fn maximum_clique(&self) -> Vec<NodeId> {
+        let mut best = Vec::new();
+        self.for_each_maximal_clique(|clique| {
+            if clique.len() > best.len() {
+                best = clique.to_vec();
+            }
+        });
+        best
+    }
+
+    /// Counts the cliques of exactly `k` nodes that contain at least one node matching `filter`.
+    pub fn count_cliques(&self, k: usize, filter: impl Fn(&N) -> bool) -> usize {
+        assert!(!self.directed, "cliques require an undirected graph");
+        if k == 0 {
+            return 0;
+        }
+
+        // Orient every edge forwards along the degeneracy ordering, so each clique is found
+        // exactly once (starting from its earliest node) and the candidate sets stay small.
+        let ordering = self.degeneracy_ordering();
+        let mut position = vec![0; self.len()];
+        for (i, &id) in ordering.iter().enumerate() {
+            position[id] = i;
+        }
+        let forward = |v: NodeId| {
+            self.neighbors(v)
+                .filter(|&n| position[n] > position[v])
+                .collect::<BTreeSet<_>>()
+        };
+
+        fn count<N>(
+            graph: &Graph<N>,
+            k: usize,
+            matched: bool,
+            candidates: &BTreeSet<NodeId>,
+            forward: &impl Fn(NodeId) -> BTreeSet<NodeId>,
+            filter: &impl Fn(&N) -> bool,
+        ) -> usize {
+            if k == 0 {
+                return matched as usize;
+            }
+            candidates
+                .iter()
+                .map(|&v| {
+                    let next = candidates.intersection(&forward(v)).copied().collect();
+                    count(
+                        graph,
+                        k - 1,
+                        matched || filter(&graph.nodes[v]),
+                        &next,
+                        forward,
+                        filter,
+                    )
+                })
+                .sum()
+        }
+
+        self.nodes()
+            .map(|v| {
+                count(
+                    self,
+                    k - 1,
+                    filter(&self.nodes[v]),
+                    &forward(v),
+                    &forward,
+                    &filter,
+                )
+            })
+            .sum()
+    }
+
+    /// Writes the graph in Graphviz DOT format.
+    pub fn write_dot(&self, w: impl std::io::Write) -> std::io::Result<()>
+    where
+        N: Display,
+    {
+        self.write_dot_with(w, "", |_| String::new(), |_, _| String::new())
+    }
+
+    /// Like `write_dot`, adding attributes for the whole graph (like `rankdir=LR`), and extra
+    /// attributes (like `shape=box, color=red`) for each node and edge.
+    pub fn write_dot_with(
+        &self,
+        mut w: impl std::io::Write,
+        graph_attributes: &str,
+        node_attributes: impl Fn(NodeId) -> String,
+        edge_attributes: impl Fn(NodeId, NodeId) -> String,
+    ) -> std::io::Result<()>
+    where
+        N: Display,
+    {
+        let (kind, arrow) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+        writeln!(w, "{kind} {{")?;
+        if !graph_attributes.is_empty() {
+            writeln!(w, "  {graph_attributes};")?;
+        }
+        for (id, node) in self.nodes.iter().enumerate() {
+            let label = format!("label=\"{}\"", dot_escape(&node.to_string()));
+            let attributes = [label, node_attributes(id)];
+            let attributes = attributes.iter().filter(|a| !a.is_empty()).join(", ");
+            writeln!(w, "  n{id} [{attributes}];")?;
+        }
+        for (a, b) in self.edges() {
+            match edge_attributes(a, b) {
+                attributes if attributes.is_empty() => writeln!(w, "  n{a} {arrow} n{b};")?,
+                attributes => writeln!(w, "  n{a} {arrow} n{b} [{attributes}];")?,
+            }
+        }
+        writeln!(w, "}}")
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn lines(text: &str) -> Vec<Line> {
+        let source: Arc<str> = "test".into();
+        text.lines()
+            .enumerate()
+            .map(|(i, text)| Line {
+                number: i + 1,
+                text: text.to_owned(),
+                source: source.clone(),
+            })
+            .collect()
+    }
+
+    fn undirected(text: &str) -> Graph<String> {
+        Graph::parse_edges(false, &lines(text), "-").unwrap()
+    }
+
+    fn names(graph: &Graph<String>, clique: &[NodeId]) -> Vec<String> {
+        clique
+            .iter()
+            .map(|&id| graph.node(id).clone())
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn parse_undirected() {
+        let graph = undirected("a-b\nb-c\nc-a\nc-d");
+        assert_eq!(graph.len(), 4);
+        assert_eq!(graph.edge_count(), 4);
+        let (a, b, d) = (
+            graph.id("a").unwrap(),
+            graph.id("b").unwrap(),
+            graph.id("d").unwrap(),
+        );
+        assert!(graph.has_edge(a, b) && graph.has_edge(b, a));
+        assert!(!graph.has_edge(a, d));
+        assert_eq!(graph.degree(graph.id("c").unwrap()), 3);
+    }
+
+    #[test]
+    fn parse_directed() {
+        let graph = Graph::<u32>::parse_edges(true, &lines("1|2\n2|3\n3|3"), "|").unwrap();
+        let [one, two, three] = [1, 2, 3].map(|n| graph.id(&n).unwrap());
+        assert!(graph.has_edge(one, two) && !graph.has_edge(two, one));
+        assert_eq!(graph.predecessors(three).collect_vec(), [two, three]);
+        // A directed self-loop is a legitimate edge.
+        assert_eq!(graph.edge_count(), 3);
+    }
+
+    #[test]
+    fn duplicate_edges_count_once() {
+        let graph = undirected("a-b\nb-a\na-b");
+        assert_eq!(graph.edge_count(), 1);
+        assert_eq!(graph.edges().count(), 1);
+        assert_eq!(graph.maximal_cliques().len(), 1);
+    }
+
+    #[test]
+    fn undirected_self_loops() {
+        let error = Graph::<String>::parse_edges(false, &lines("a-b\nc-c"), "-").unwrap_err();
+        assert!(error.to_string().starts_with("test:2:1: "), "{error}");
+
+        let mut graph = Graph::undirected();
+        graph.add_edge("a", "b");
+        graph.add_edge("a", "a");
+        assert_eq!(graph.edge_count(), 1);
+        assert_eq!(graph.maximal_cliques().len(), 1);
+        assert_eq!(graph.maximum_clique().len(), 2);
+    }
+
+    #[test]
+    fn parse_errors_are_located() {
+        let error = Graph::<u32>::parse_edges(true, &lines("1|2\n3|x"), "|").unwrap_err();
+        assert!(
+            error.to_string().starts_with("test:2:3: invalid node 'x'"),
+            "{error}"
+        );
+        let error = Graph::<u32>::parse_edges(true, &lines("1-2"), "|").unwrap_err();
+        assert!(
+            error.to_string().starts_with("test:1:1: expected '|'"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn cliques() {
+        // Two triangles sharing the edge b-c, a square c-d-e-f, and an isolated edge g-h.
+        let graph = undirected("a-b\nb-c\nc-a\nb-d\nd-c\nd-e\ne-f\nf-c\ng-h");
+        let cliques = graph
+            .maximal_cliques()
+            .iter()
+            .map(|clique| names(&graph, clique))
+            .sorted()
+            .collect_vec();
+        assert_eq!(
+            cliques,
+            [
+                vec!["a", "b", "c"],
+                vec!["b", "c", "d"],
+                vec!["c", "f"],
+                vec!["d", "e"],
+                vec!["e", "f"],
+                vec!["g", "h"],
+            ]
+        );
+        assert_eq!(graph.maximum_clique().len(), 3);
+        assert_eq!(graph.count_cliques(3, |_| true), 2);
+        assert_eq!(graph.count_cliques(3, |n| n == "a"), 1);
+        assert_eq!(graph.count_cliques(2, |_| true), graph.edge_count());
+        assert_eq!(graph.count_cliques(4, |_| true), 0);
+    }
+
+    #[test]
+    fn complete_graph() {
+        let mut graph = Graph::undirected();
+        for a in 0..6 {
+            for b in 0..a {
+                graph.add_edge(a, b);
+            }
+        }
+        assert_eq!(graph.edge_count(), 15);
+        assert_eq!(graph.maximal_cliques().len(), 1);
+        assert_eq!(graph.maximum_clique().len(), 6);
+        // 6 choose 3
+        assert_eq!(graph.count_cliques(3, |_| true), 20);
+    }
+}
@@ -1,12 +1,31 @@
-use std::io::{BufReader, Read, Seek};
+use std::{
+    io::{BufRead, BufReader, Read, Seek, Write},
+    sync::Arc,
+};
 
 use anyhow::{Result, bail};
 
+use itertools::Itertools;
+use parse::Line;
+
+pub mod cycle;
+pub mod graph;
+pub mod order;
+pub mod par;
+pub mod parse;
+pub mod random;
+pub mod reference;
+pub mod vm;
+
 pub mod prelude {
     pub use std::collections::HashMap;
     pub use std::io::{BufRead, Read};
 
-    pub use super::chal;
+    pub use super::cycle::{Cycle, brent, find_cycle, state_at};
+    pub use super::graph::Graph;
+    pub use super::order::{PartialOrder, SortError};
+    pub use super::parse::{Line, LineContext, Parser, Section};
+    pub use super::{chal, chal_check, chal_gen};
     pub use anyhow::{Context, Ok, Result, anyhow, bail, ensure};
 
     pub use itertools::Itertools;
@@ -114,50 +133,320 @@ pub mod prelude {
 pub struct Challenge {
     pub part1: bool,
     pub part2: bool,
-    pub input: BufReader<Box<dyn Read>>,
+    pub input: Input,
+    pub options: Options,
+}
+
+/// The puzzle input, along with a name identifying it in error messages.
+pub struct Input {
+    name: Arc<str>,
+    reader: BufReader<Box<dyn Read>>,
+}
+
+impl Input {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Reads the rest of the input as sections separated by blank lines.
+    pub fn sections(self) -> Result<Vec<parse::Section>> {
+        Ok(parse::sections(
+            self.numbered_lines().try_collect::<_, Vec<_>, _>()?,
+        ))
+    }
+
+    /// Reads the rest of the input as exactly two sections separated by a blank line, parsing
+    /// them with `first` and `second`.
+    pub fn two_sections<A, B>(
+        self,
+        first: impl FnOnce(parse::Section) -> Result<A>,
+        second: impl FnOnce(parse::Section) -> Result<B>,
+    ) -> Result<(A, B)> {
+        let lines: Vec<_> = self.numbered_lines().try_collect()?;
+        let (a, b) = parse::two_sections(lines)?;
+        Ok((first(a)?, second(b)?))
+    }
+
+    /// Calls `f` with each line of the input in turn, reusing a single buffer so that inputs of
+    /// any size can be processed without holding them in memory.
+    pub fn for_each_line(mut self, mut f: impl FnMut(&Line) -> Result<()>) -> Result<()> {
+        let mut line = Line {
+            number: 0,
+            text: String::new(),
+            source: self.name.clone(),
+        };
+        loop {
+            line.text.clear();
+            if self.reader.read_line(&mut line.text)? == 0 {
+                return Ok(());
+            }
+            if line.text.ends_with('\n') {
+                line.text.pop();
+                if line.text.ends_with('\r') {
+                    line.text.pop();
+                }
+            }
+            line.number += 1;
+            f(&line)?;
+        }
+    }
+
+    /// Calls `f` on every line of the input in parallel, summing the results. Only a batch of
+    /// results is held at a time, and they are added up in input order.
+    pub fn par_sum_lines<T: Send + std::iter::Sum>(
+        self,
+        f: impl Fn(&Line) -> Result<T> + Sync,
+    ) -> Result<T> {
+        let mut sums = Vec::new();
+        self.par_batches(f, |batch| sums.push(batch.into_iter().sum()))?;
+        Ok(sums.into_iter().sum())
+    }
+
+    fn par_batches<T: Send>(
+        self,
+        f: impl Fn(&Line) -> Result<T> + Sync,
+        mut consume: impl FnMut(Vec<T>),
+    ) -> Result<()> {
+        const BATCH_SIZE: usize = 1 << 12;
+        let mut lines = self.numbered_lines();
+        loop {
+            let batch: Vec<Line> = lines.by_ref().take(BATCH_SIZE).try_collect()?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            consume(par::try_map(&batch, &f)?);
+        }
+    }
+
+    /// Iterates over the lines of the input, numbering them from 1.
+    pub fn numbered_lines(self) -> impl Iterator<Item = std::io::Result<Line>> {
+        let name = self.name;
+        self.reader.lines().enumerate().map(move |(i, text)| {
+            Ok(Line {
+                number: i + 1,
+                text: text?,
+                source: name.clone(),
+            })
+        })
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl BufRead for Input {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+/// Command-line options of the form `--name` or `--name=value`.
+pub struct Options(std::collections::HashMap<String, Option<String>>);
+
+impl Options {
+    /// Returns true if `--name` (or `--name=value`) was passed on the command line.
+    pub fn flag(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Returns the value passed on the command line as `--name=value`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name)?.as_deref()
+    }
 }
 
 pub fn chal() -> Result<Challenge> {
-    let mut args = std::env::args().fuse();
-
-    let (part2, infile) = match (
-        args.next().as_deref(),
-        args.next().as_deref(),
-        args.next(),
-        args.next().as_deref(),
-    ) {
-        (_, None | Some("1" | "part1"), infile, None) => (false, infile),
-        (_, Some("2" | "part2"), infile, None) => (true, infile),
-        (bin, _, _, _) => {
-            eprintln!(
-                "usage: {} [part1|part2 [infile]]",
-                bin.unwrap_or("aoc_2024")
-            );
+    chal_inner(None, None)
+}
+
+/// A function writing a random puzzle input of roughly the given size (in records, such as lines
+/// or grid rows) to its output. Generators may take additional parameters as options.
+pub type Generator<'a> =
+    dyn Fn(&mut random::Rng, usize, &Options, &mut dyn Write) -> Result<()> + 'a;
+
+/// A function solving part 1 (or part 2, if the flag is set) of a puzzle input independently of
+/// the real solver, or returning None if it cannot solve that part.
+pub type Reference<'a> = dyn Fn(&str, bool, &Options) -> Result<Option<String>> + 'a;
+
+/// Like `chal`, but additionally supports `dayN gen [seed] [size]`, which writes an input made by
+/// `generator` to stdout and exits.
+pub fn chal_gen(
+    generator: impl Fn(&mut random::Rng, usize, &Options, &mut dyn Write) -> Result<()>,
+) -> Result<Challenge> {
+    chal_inner(Some(Box::new(generator)), None)
+}
+
+/// Like `chal_gen`, but additionally supports `dayN check [seeds] [size]`, which runs this
+/// binary on inputs made by `generator` for each seed up to `seeds` and compares its answers
+/// against those from `reference`.
+pub fn chal_check(
+    generator: impl Fn(&mut random::Rng, usize, &Options, &mut dyn Write) -> Result<()>,
+    reference: impl Fn(&str, bool, &Options) -> Result<Option<String>>,
+) -> Result<Challenge> {
+    chal_inner(Some(Box::new(generator)), Some(Box::new(reference)))
+}
+
+fn chal_inner(
+    generator: Option<Box<Generator>>,
+    reference: Option<Box<Reference>>,
+) -> Result<Challenge> {
+    let (option_args, args): (Vec<_>, Vec<_>) = std::env::args()
+        .enumerate()
+        .partition(|(i, arg)| *i > 0 && arg.starts_with("--") && arg.len() > 2);
+    let option_args = option_args.into_iter().map(|(_, arg)| arg).collect_vec();
+    let options = Options(
+        option_args
+            .iter()
+            .map(|arg| match arg[2..].split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None => (arg[2..].to_owned(), None),
+            })
+            .collect(),
+    );
+    par::configure(&options)?;
+    let args = args.into_iter().map(|(_, arg)| arg).collect_vec();
+    let bin = args.first().map_or("aoc_2024", String::as_str);
+
+    let (part2, infile) = match args.iter().skip(1).map(String::as_str).collect_vec()[..] {
+        [] => (false, None),
+        ["1" | "part1", ref infile @ ..] if infile.len() <= 1 => (false, infile.first().copied()),
+        ["2" | "part2", ref infile @ ..] if infile.len() <= 1 => (true, infile.first().copied()),
+        ["gen", ref params @ ..] if params.len() <= 2 => {
+            let Some(generator) = generator else {
+                bail!("{bin} has no input generator");
+            };
+            let seed = params.first().map_or(Ok(0), |s| s.parse())?;
+            let size = params.get(1).map_or(Ok(1000), |s| s.parse())?;
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            generator(&mut random::Rng::new(seed), size, &options, &mut out)?;
+            out.flush()?;
+            std::process::exit(0);
+        }
+        ["check", ref params @ ..] if params.len() <= 2 => {
+            let (Some(generator), Some(reference)) = (generator, reference) else {
+                bail!("{bin} has no reference solver");
+            };
+            let seeds = params.first().map_or(Ok(100), |s| s.parse())?;
+            let size = params.get(1).map_or(Ok(10), |s| s.parse())?;
+            let failures = check(
+                &*generator,
+                &*reference,
+                seeds,
+                size,
+                &options,
+                &option_args,
+            )?;
+            if failures > 0 {
+                eprintln!("{failures} answers differed from the reference solver");
+                std::process::exit(1);
+            }
+            eprintln!("all answers for {seeds} inputs of size {size} matched");
+            std::process::exit(0);
+        }
+        _ => {
+            eprintln!("usage: {bin} [part1|part2 [infile]] [--option[=value]...]");
+            eprintln!("       {bin} [part1|part2 [infile]] [--sequential | --threads=N]");
+            eprintln!("       {bin} gen [seed] [size]");
+            eprintln!("       {bin} check [seeds] [size]");
             std::process::exit(1);
         }
     };
 
-    let input: BufReader<Box<dyn Read>> = match infile.as_deref() {
-        None => BufReader::new(Box::new(retrieve_input()?)),
-        Some("-") => BufReader::new(Box::new(std::io::stdin().lock())),
-        Some(file) => BufReader::new(Box::new(std::fs::File::open(file)?)),
+    let (name, reader): (_, Box<dyn Read>) = match infile {
+        None => {
+            let (path, file) = retrieve_input()?;
+            (path, Box::new(file))
+        }
+        Some("-") => ("<stdin>".to_owned(), Box::new(std::io::stdin().lock())),
+        Some(file) => (file.to_owned(), Box::new(std::fs::File::open(file)?)),
+    };
+    let input = Input {
+        name: name.into(),
+        // A large buffer makes streaming through big generated inputs cheaper.
+        reader: BufReader::with_capacity(1 << 20, reader),
     };
 
     Ok(Challenge {
         part1: !part2,
         part2,
         input,
+        options,
     })
 }
 
-fn retrieve_input() -> Result<impl Read> {
+/// Compares the answers of this binary against the reference solver, for inputs made by
+/// `generator` from seeds `0..seeds`. Returns the number of answers which differed.
+fn check(
+    generator: &Generator,
+    reference: &Reference,
+    seeds: u64,
+    size: usize,
+    options: &Options,
+    option_args: &[String],
+) -> Result<usize> {
+    let exe = std::env::current_exe()?;
+    let name = exe.file_stem().unwrap_or_default().to_string_lossy();
+    let mut failures = 0;
+    for seed in 0..seeds {
+        let mut input = Vec::new();
+        generator(&mut random::Rng::new(seed), size, options, &mut input)?;
+        let text = std::str::from_utf8(&input)?;
+
+        for part in [1, 2] {
+            let Some(expected) = reference(text, part == 2, options)? else {
+                continue;
+            };
+
+            // Run the solver in a separate process, so that panics and global state in one run
+            // don't affect the others.
+            let mut child = std::process::Command::new(&exe)
+                .arg(format!("part{part}"))
+                .arg("-")
+                .args(option_args)
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .spawn()?;
+            let mut stdin = child.stdin.take().unwrap();
+            let output = std::thread::scope(|s| {
+                s.spawn(move || stdin.write_all(text.as_bytes()));
+                child.wait_with_output()
+            })?;
+
+            let actual = String::from_utf8_lossy(&output.stdout);
+            if !output.status.success() || actual.trim() != expected.trim() {
+                failures += 1;
+                eprintln!(
+                    "seed {seed}, part {part}: expected {}, got {} ({})",
+                    expected.trim(),
+                    actual.trim(),
+                    output.status
+                );
+                eprintln!(
+                    "  reproduce with: {name} gen {seed} {size} | {name} part{part} - {}",
+                    option_args.join(" ")
+                );
+            }
+        }
+    }
+    Ok(failures)
+}
+
+/// Opens the input file for the current day, downloading it if necessary.
+fn retrieve_input() -> Result<(String, impl Read)> {
     let bin = std::env::current_exe()?;
     let bin = bin.file_stem().unwrap().to_str().unwrap();
     assert!(bin.starts_with("day"));
     let path = format!("input/{bin}.txt");
 
     match std::fs::File::open(&path) {
-        Ok(file) => return Ok(file),
+        Ok(file) => return Ok((path, file)),
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
         Err(e) => return Err(e.into()),
     };
@@ -187,9 +476,9 @@ fn retrieve_input() -> Result<impl Read> {
         .error_for_status()?;
 
     std::fs::create_dir_all("input")?;
-    let mut file = std::fs::File::create_new(path)?;
+    let mut file = std::fs::File::create_new(&path)?;
     std::io::copy(&mut response, &mut file)?;
 
     file.rewind()?;
-    Ok(file)
+    Ok((path, file))
 }
@@ -0,0 +1,271 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    fmt::{Debug, Display},
+    hash::Hash,
+    str::FromStr,
+};
+
+use anyhow::Result;
+use itertools::Itertools;
+
+use crate::graph::{Graph, NodeId};
+use crate::parse::Line;
+
+/// A precedence relation built from rules of the form "`a` must come before `b`".
+///
+/// The relation need not be transitive or even acyclic as a whole; it only has to be acyclic when
+/// restricted to the elements of a particular sequence in order to sort that sequence.
+#[derive(Clone, Debug)]
+pub struct PartialOrder<N> {
+    rules: Graph<N>,
+}
+
+/// The elements of a sequence that could not be ordered because the rules between them form a
+/// cycle. Each element must come before the next, and the last must come before the first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderCycle<N>(pub Vec<N>);
+
+impl<N: Display> Display for OrderCycle<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ordering rules form a cycle: ")?;
+        for n in &self.0 {
+            write!(f, "{n} -> ")?;
+        }
+        match self.0.first() {
+            Some(n) => write!(f, "{n}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<N: Debug + Display> std::error::Error for OrderCycle<N> {}
+
+/// Why a sequence could not be sorted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SortError<N> {
+    /// The rules between the elements of the sequence form a cycle.
+    Cycle(OrderCycle<N>),
+    /// An element appears more than once, so there is no single place to put it.
+    Duplicate(N),
+}
+
+impl<N: Display> Display for SortError<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortError::Cycle(cycle) => write!(f, "{cycle}"),
+            SortError::Duplicate(n) => write!(f, "{n} appears more than once"),
+        }
+    }
+}
+
+impl<N: Debug + Display> std::error::Error for SortError<N> {}
+
+impl<N: Hash + Eq + Clone> PartialOrder<N> {
+    /// Parses rules of the form `a<sep>b`, meaning `a` must come before `b`.
+    pub fn parse(lines: &[Line], separator: &str) -> Result<Self>
+    where
+        N: FromStr<Err: Display>,
+    {
+        Ok(PartialOrder {
+            rules: Graph::parse_edges(true, lines, separator)?,
+        })
+    }
+
+    pub fn from_graph(rules: Graph<N>) -> Self {
+        assert!(rules.is_directed(), "precedence rules must be directed");
+        PartialOrder { rules }
+    }
+
+    pub fn graph(&self) -> &Graph<N> {
+        &self.rules
+    }
+
+    /// Returns true if there is a rule stating that `a` must come before `b`.
+    pub fn precedes(&self, a: &N, b: &N) -> bool {
+        match (self.rules.id(a), self.rules.id(b)) {
+            (Some(a), Some(b)) => self.rules.has_edge(a, b),
+            _ => false,
+        }
+    }
+
+    /// Returns true if no element of `seq` appears after something it must come before. If an
+    /// element is repeated, every occurrence must respect the rules.
+    pub fn check(&self, seq: &[N]) -> bool {
+        // The first and last positions of each element.
+        let mut spans = HashMap::<NodeId, (usize, usize)>::new();
+        for (i, n) in seq.iter().enumerate() {
+            if let Some(id) = self.rules.id(n) {
+                spans
+                    .entry(id)
+                    .and_modify(|span| span.1 = i)
+                    .or_insert((i, i));
+            }
+        }
+        spans.iter().all(|(&id, &(first, _))| {
+            self.rules
+                .predecessors(id)
+                .all(|before| spans.get(&before).is_none_or(|&(_, last)| last < first))
+        })
+    }
+
+    /// Reorders `seq` to respect every rule between its elements, which must be distinct.
+    /// Elements not constrained relative to one another keep their original order.
+    pub fn sort(&self, seq: &[N]) -> Result<Vec<N>, SortError<N>> {
+        let positions = self.positions(seq)?;
+        let ids = seq.iter().map(|n| self.rules.id(n)).collect_vec();
+
+        // Kahn's algorithm, considering only the rules between elements present in the sequence.
+        let mut in_degree = ids
+            .iter()
+            .map(|id| {
+                id.map_or(0, |id| {
+                    self.rules
+                        .predecessors(id)
+                        .filter(|p| positions.contains_key(p))
+                        .count()
+                })
+            })
+            .collect_vec();
+        let mut ready: BinaryHeap<_> = (0..seq.len())
+            .filter(|&i| in_degree[i] == 0)
+            .map(Reverse)
+            .collect();
+
+        let mut sorted = Vec::with_capacity(seq.len());
+        while let Some(Reverse(i)) = ready.pop() {
+            sorted.push(seq[i].clone());
+            for next in ids[i].into_iter().flat_map(|id| self.rules.neighbors(id)) {
+                if let Some(&j) = positions.get(&next) {
+                    in_degree[j] -= 1;
+                    if in_degree[j] == 0 {
+                        ready.push(Reverse(j));
+                    }
+                }
+            }
+        }
+
+        if sorted.len() < seq.len() {
+            let remaining = (0..seq.len())
+                .filter(|&i| in_degree[i] > 0)
+                .filter_map(|i| ids[i])
+                .collect_vec();
+            // If no cycle can be traced, fall back to reporting everything left unsorted.
+            let cycle = self.find_cycle(&remaining).unwrap_or(remaining);
+            let cycle = cycle.into_iter().map(|id| self.rules.node(id).clone());
+            return Err(SortError::Cycle(OrderCycle(cycle.collect())));
+        }
+        Ok(sorted)
+    }
+
+    /// Maps the nodes of `seq` which appear in the rules to their index in `seq`, failing if any
+    /// element is repeated.
+    fn positions(&self, seq: &[N]) -> Result<HashMap<NodeId, usize>, SortError<N>> {
+        let mut seen = HashSet::new();
+        let mut positions = HashMap::new();
+        for (i, n) in seq.iter().enumerate() {
+            if !seen.insert(n) {
+                return Err(SortError::Duplicate(n.clone()));
+            }
+            if let Some(id) = self.rules.id(n) {
+                positions.insert(id, i);
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Finds a cycle among `remaining`, the nodes left over by a topological sort. Every such
+    /// node has a predecessor among them, so walking backwards must eventually repeat a node.
+    fn find_cycle(&self, remaining: &[NodeId]) -> Option<Vec<NodeId>> {
+        let remaining: HashSet<_> = remaining.iter().copied().collect();
+        let mut path = Vec::new();
+        let mut seen = HashMap::new();
+        let mut id = *remaining.iter().min()?;
+        while !seen.contains_key(&id) {
+            seen.insert(id, path.len());
+            path.push(id);
+            id = self
+                .rules
+                .predecessors(id)
+                .find(|p| remaining.contains(p))?;
+        }
+        let mut cycle = path.split_off(seen[&id]);
+        cycle.reverse();
+        Some(cycle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn rules(text: &str) -> PartialOrder<u32> {
+        let source: Arc<str> = "test".into();
+        let lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, text)| Line {
+                number: i + 1,
+                text: text.to_owned(),
+                source: source.clone(),
+            })
+            .collect_vec();
+        PartialOrder::parse(&lines, "|").unwrap()
+    }
+
+    #[test]
+    fn valid_orders() {
+        let order = rules("1|2\n2|3\n1|4");
+        assert!(order.precedes(&1, &2));
+        assert!(!order.precedes(&2, &1));
+        // Only direct rules count; the relation isn't closed transitively.
+        assert!(!order.precedes(&1, &3));
+
+        assert!(order.check(&[1, 2, 3]));
+        assert!(order.check(&[1, 4, 2, 5, 3]));
+        assert!(order.check(&[3, 1]));
+        assert!(order.check(&[]));
+        assert!(!order.check(&[2, 1]));
+        assert!(!order.check(&[3, 2, 1]));
+
+        assert_eq!(order.sort(&[3, 2, 1]), Ok(vec![1, 2, 3]));
+        // Unconstrained elements keep their relative order.
+        assert_eq!(order.sort(&[5, 4, 2, 1]), Ok(vec![5, 1, 4, 2]));
+        assert_eq!(order.sort(&[]), Ok(vec![]));
+        assert!(order.check(&order.sort(&[4, 3, 2, 1]).unwrap()));
+    }
+
+    #[test]
+    fn cycles() {
+        let order = rules("1|2\n2|3\n3|1\n3|4");
+        // The cycle only matters if all of its elements are present.
+        assert_eq!(order.sort(&[3, 2, 4]), Ok(vec![2, 3, 4]));
+        let Err(SortError::Cycle(OrderCycle(cycle))) = order.sort(&[4, 3, 2, 1]) else {
+            panic!("expected a cycle");
+        };
+        // The cycle may start anywhere.
+        assert!(
+            (0..3).any(|i| cycle[i..].iter().chain(&cycle[..i]).eq(&[1, 2, 3])),
+            "{cycle:?}"
+        );
+
+        let order = rules("1|1");
+        assert_eq!(
+            order.sort(&[2, 1]),
+            Err(SortError::Cycle(OrderCycle(vec![1])))
+        );
+        assert!(!order.check(&[1]));
+    }
+
+    #[test]
+    fn duplicates() {
+        let order = rules("1|2");
+        assert_eq!(order.sort(&[2, 1, 2]), Err(SortError::Duplicate(2)));
+        assert_eq!(order.sort(&[3, 3]), Err(SortError::Duplicate(3)));
+        assert!(order.check(&[1, 1, 2, 2]));
+        assert!(!order.check(&[1, 2, 1]));
+        assert!(!order.check(&[2, 1, 2]));
+    }
+}
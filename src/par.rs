@@ -0,0 +1,56 @@
+//! Running independent work for each record of the input (a line, a section, ...) on all cores.
+//!
+//! Results always come back in input order, so neither the answer nor which error gets reported
+//! depends on how the work was scheduled. Pass `--sequential` to do everything on one thread, or
+//! `--threads=N` to choose the number of threads.
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+
+use crate::Options;
+
+/// Applies `f` to every item in parallel, returning the results in the same order as the items.
+pub fn map<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Sync) -> Vec<U> {
+    items.par_iter().map(&f).collect()
+}
+
+/// Like `map`, but fails with the error from the first item (in input order) for which `f`
+/// failed.
+pub fn try_map<T: Sync, U: Send>(
+    items: &[T],
+    f: impl Fn(&T) -> Result<U> + Sync,
+) -> Result<Vec<U>> {
+    map(items, f).into_iter().collect()
+}
+
+/// Splits `items` into chunks of `chunk_size`, folding the chunks each thread handles into an
+/// accumulator made by `init`, then merges the accumulators with `merge`. Unlike `map`, this
+/// only holds a few accumulators at once, however many chunks there are.
+pub fn fold_chunks<T: Sync, A: Send>(
+    items: &[T],
+    chunk_size: usize,
+    init: impl Fn() -> A + Sync + Send,
+    fold: impl Fn(A, &[T]) -> A + Sync + Send,
+    merge: impl Fn(A, A) -> A + Sync + Send,
+) -> A {
+    items
+        .par_chunks(chunk_size)
+        .fold(&init, fold)
+        .reduce(&init, merge)
+}
+
+/// Sets up the thread pool according to the `--sequential` and `--threads` options.
+pub(crate) fn configure(options: &Options) -> Result<()> {
+    let threads = if options.flag("sequential") {
+        1
+    } else if let Some(threads) = options.get("threads") {
+        threads.parse().context("invalid --threads")?
+    } else {
+        // Let rayon decide.
+        return Ok(());
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()?;
+    Ok(())
+}
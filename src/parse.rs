@@ -0,0 +1,321 @@
+//! Helpers for parsing puzzle input, reporting errors by line and column.
+
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+use anyhow::{Result, anyhow};
+
+/// A cursor over a single line of input.
+#[derive(Clone, Debug)]
+pub struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    line: usize,
+    source: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The current column, counting from 1.
+    pub fn column(&self) -> usize {
+        self.input[..self.pos].chars().count() + 1
+    }
+
+    /// Creates an error pointing at the current position.
+    pub fn error(&self, message: impl Display) -> anyhow::Error {
+        error_at(self.source, self.line, self.input, self.column(), message)
+    }
+
+    /// The unparsed remainder of the line.
+    pub fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pos == self.input.len()
+    }
+
+    /// Fails unless the whole line has been parsed.
+    pub fn finish(&self) -> Result<()> {
+        if self.is_done() {
+            Ok(())
+        } else {
+            Err(self.error(format!("unexpected '{}'", self.rest())))
+        }
+    }
+
+    /// Consumes `literal` if the remaining input starts with it.
+    pub fn eat(&mut self, literal: &str) -> bool {
+        let found = self.rest().starts_with(literal);
+        if found {
+            self.pos += literal.len();
+        }
+        found
+    }
+
+    pub fn literal(&mut self, literal: &str) -> Result<()> {
+        if self.eat(literal) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{literal}'")))
+        }
+    }
+
+    pub fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes characters as long as `f` returns true, returning them.
+    pub fn take_while(&mut self, mut f: impl FnMut(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Consumes everything up to (but not including) `delimiter`, or to the end of the line if
+    /// there is no delimiter.
+    pub fn until(&mut self, delimiter: &str) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(delimiter).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Parses an integer with an optional sign.
+    pub fn int<T: FromStr<Err: Display>>(&mut self) -> Result<T> {
+        let start = self.clone();
+        let sign = if self.eat("-") || self.eat("+") { 1 } else { 0 };
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if digits.is_empty() {
+            *self = start;
+            return Err(self.error("expected an integer"));
+        }
+        let text = &start.rest()[..sign + digits.len()];
+        text.parse()
+            .map_err(|e| start.error(format!("invalid integer '{text}': {e}")))
+    }
+
+    /// Parses a value using its `FromStr` implementation, consuming the rest of the line.
+    pub fn value<T: FromStr<Err: Display>>(&mut self) -> Result<T> {
+        let start = self.clone();
+        let text = self.rest();
+        self.pos = self.input.len();
+        text.parse()
+            .map_err(|e| start.error(format!("invalid value '{text}': {e}")))
+    }
+
+    /// Parses a line of the form `key: value`, where `value` is parsed by `f`.
+    pub fn field<T>(&mut self, key: &str, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.literal(key)?;
+        self.literal(":")?;
+        self.skip_whitespace();
+        f(self)
+    }
+
+    /// Parses one or more items separated by `separator`.
+    pub fn separated<T>(
+        &mut self,
+        separator: &str,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut items = vec![item(self)?];
+        while self.eat(separator) {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+}
+
+/// Creates an error pointing at `column` (counting from 1) of line `number` of `source`, quoting
+/// the offending line.
+pub fn error_at(
+    source: &str,
+    number: usize,
+    text: &str,
+    column: usize,
+    message: impl Display,
+) -> anyhow::Error {
+    anyhow!(
+        "{source}:{number}:{column}: {message}\n    {text}\n    {:>column$}",
+        "^"
+    )
+}
+
+/// A line of input, remembering where it came from for error messages.
+#[derive(Clone, Debug)]
+pub struct Line {
+    /// The line number, counting from 1.
+    pub number: usize,
+    pub text: String,
+    pub source: Arc<str>,
+}
+
+impl Line {
+    pub fn parser(&self) -> Parser<'_> {
+        Parser {
+            input: &self.text,
+            pos: 0,
+            line: self.number,
+            source: &self.source,
+        }
+    }
+
+    /// Creates an error pointing at `column` (counting from 1) of this line.
+    pub fn error(&self, column: usize, message: impl Display) -> anyhow::Error {
+        error_at(&self.source, self.number, &self.text, column, message)
+    }
+
+    /// Extracts every integer from the line, ignoring any other text. A `-` immediately before
+    /// a number is treated as a sign unless it follows a letter or digit, as in `x-1`.
+    pub fn extract_ints<T: FromStr<Err: Display>>(&self) -> Result<Vec<T>> {
+        let bytes = self.text.as_bytes();
+        let mut ints = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            if !bytes[i].is_ascii_digit() {
+                i += 1;
+                continue;
+            }
+            let mut start = i;
+            if start > 0
+                && bytes[start - 1] == b'-'
+                && (start < 2 || !bytes[start - 2].is_ascii_alphanumeric())
+            {
+                start -= 1;
+            }
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            let text = &self.text[start..i];
+            let column = self.text[..start].chars().count() + 1;
+            ints.push(text.parse().at(self, column)?);
+        }
+        Ok(ints)
+    }
+
+    /// Extracts exactly `N` integers from the line, as with `extract_ints`.
+    pub fn int_array<T: FromStr<Err: Display>, const N: usize>(&self) -> Result<[T; N]> {
+        let ints = self.extract_ints()?;
+        let found = ints.len();
+        ints.try_into()
+            .map_err(|_| self.error(1, format!("expected {N} integers, found {found}")))
+    }
+}
+
+/// Attaches the location of the input being parsed to an error.
+pub trait LineContext<T> {
+    /// Reports an error at `column` (counting from 1) of `line`.
+    fn at(self, line: &Line, column: usize) -> Result<T>;
+}
+
+impl<T, E: Display> LineContext<T> for std::result::Result<T, E> {
+    fn at(self, line: &Line, column: usize) -> Result<T> {
+        self.map_err(|e| line.error(column, e))
+    }
+}
+
+/// A group of consecutive non-blank lines.
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub lines: Vec<Line>,
+}
+
+impl Section {
+    /// The line number of the first line of the section.
+    pub fn start(&self) -> usize {
+        self.lines[0].number
+    }
+
+    /// The line number just past the end of the section.
+    fn end(&self) -> usize {
+        self.start() + self.lines.len()
+    }
+
+    fn source(&self) -> &str {
+        &self.lines[0].source
+    }
+
+    /// Returns a parser for the `i`th line of the section, failing if there are too few lines.
+    pub fn line(&self, i: usize) -> Result<Parser<'_>> {
+        match self.lines.get(i) {
+            Some(line) => Ok(line.parser()),
+            None => Err(anyhow!(
+                "{}:{}: expected at least {} lines in this section",
+                self.source(),
+                self.end(),
+                i + 1
+            )),
+        }
+    }
+
+    /// Fails unless the section has exactly `n` lines.
+    pub fn expect_lines(&self, n: usize) -> Result<()> {
+        match self.lines.len() {
+            len if len < n => Err(anyhow!(
+                "{}:{}: expected {n} lines in this section, found {len}",
+                self.source(),
+                self.end()
+            )),
+            len if len > n => Err(anyhow!(
+                "{}:{}: expected a blank line after {n} lines",
+                self.source(),
+                self.start() + n
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Parses every line of the section with `f`, which must consume the whole line.
+    pub fn parse_lines<T>(&self, mut f: impl FnMut(&mut Parser) -> Result<T>) -> Result<Vec<T>> {
+        self.lines
+            .iter()
+            .map(|line| {
+                let mut parser = line.parser();
+                let value = f(&mut parser)?;
+                parser.finish()?;
+                Ok(value)
+            })
+            .collect()
+    }
+}
+
+/// Splits lines into sections separated by one or more blank lines (which may contain
+/// whitespace). Blank lines at the start or end of the input are ignored.
+pub fn sections(lines: impl IntoIterator<Item = Line>) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut current = Vec::new();
+    for line in lines {
+        if line.text.trim().is_empty() {
+            if !current.is_empty() {
+                sections.push(Section {
+                    lines: std::mem::take(&mut current),
+                });
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        sections.push(Section { lines: current });
+    }
+    sections
+}
+
+/// Splits lines into exactly two sections, such as a set of rules followed by a list of queries.
+pub fn two_sections(lines: impl IntoIterator<Item = Line>) -> Result<(Section, Section)> {
+    let mut lines = lines.into_iter().peekable();
+    let source = lines.peek().map(|line| line.source.clone());
+    let sections = sections(lines);
+    match <[Section; 2]>::try_from(sections) {
+        Ok([first, second]) => Ok((first, second)),
+        Err(sections) => Err(anyhow!(
+            "{}: expected two sections separated by a blank line, found {}",
+            source.as_deref().unwrap_or("input"),
+            sections.len()
+        )),
+    }
+}
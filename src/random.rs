@@ -0,0 +1,80 @@
+//! A small deterministic random number generator for producing synthetic puzzle inputs.
+
+use std::ops::Range;
+
+/// The SplitMix64 generator: tiny, fast, and good enough for generating test data.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value uniformly distributed in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "empty range");
+        // Multiply-shift rather than modulo, to avoid bias towards small values.
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    pub fn range(&mut self, range: Range<i64>) -> i64 {
+        assert!(range.start < range.end, "empty range");
+        let len = range.end.abs_diff(range.start);
+        range.start.wrapping_add_unsigned(self.below(len))
+    }
+
+    /// Returns true with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u64 + 1) as usize);
+        }
+    }
+}
+
+/// Generates a perfect maze (one with exactly one path between any two open tiles) as rows of
+/// `#` and `.`. Open tiles are those with both coordinates odd, plus the passages between them,
+/// so the dimensions are rounded up to odd numbers of at least 3.
+pub fn maze(rng: &mut Rng, width: usize, height: usize) -> Vec<Vec<u8>> {
+    let (width, height) = (width.max(3) | 1, height.max(3) | 1);
+    let mut grid = vec![vec![b'#'; width]; height];
+
+    // Carve passages with a randomized depth-first search.
+    grid[1][1] = b'.';
+    let mut stack = vec![(1usize, 1usize)];
+    while let Some(&(x, y)) = stack.last() {
+        let mut neighbors = [(0, -2), (2, 0), (0, 2), (-2, 0)]
+            .into_iter()
+            .filter_map(|(dx, dy)| {
+                let (nx, ny) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+                (nx < width && ny < height && grid[ny][nx] == b'#').then_some((nx, ny))
+            })
+            .collect::<Vec<_>>();
+        if neighbors.is_empty() {
+            stack.pop();
+            continue;
+        }
+        rng.shuffle(&mut neighbors);
+        let (nx, ny) = neighbors[0];
+        grid[(y + ny) / 2][(x + nx) / 2] = b'.';
+        grid[ny][nx] = b'.';
+        stack.push((nx, ny));
+    }
+    grid
+}
@@ -0,0 +1,220 @@
+//! Deliberately naive solutions to some of the puzzles, for cross-checking the real solvers on
+//! small inputs. These favor being obviously correct over being fast, and share no code with the
+//! solvers they check.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::{Context, Result, bail, ensure};
+
+/// Every integer in `input`, ignoring signs and any other text.
+fn numbers(input: &str) -> Result<Vec<u64>> {
+    input
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().with_context(|| format!("invalid number '{s}'")))
+        .collect()
+}
+
+/// Day 11: blinks at the stones `blinks` times, keeping every stone in a list.
+pub fn day11(input: &str, blinks: usize) -> Result<u64> {
+    let mut stones = numbers(input)?;
+    for _ in 0..blinks {
+        let mut next = Vec::new();
+        for stone in stones {
+            let digits = stone.to_string();
+            if stone == 0 {
+                next.push(1);
+            } else if digits.len() % 2 == 0 {
+                let (left, right) = digits.split_at(digits.len() / 2);
+                next.push(left.parse()?);
+                next.push(right.parse()?);
+            } else {
+                next.push(stone * 2024);
+            }
+        }
+        stones = next;
+    }
+    Ok(stones.len() as u64)
+}
+
+/// Day 13: tries every combination of up to 100 presses of each button.
+pub fn day13(input: &str) -> Result<u64> {
+    let numbers = numbers(input)?;
+    ensure!(
+        numbers.len() % 6 == 0,
+        "expected six numbers per machine, found {}",
+        numbers.len()
+    );
+    let mut total = 0;
+    for machine in numbers.chunks(6) {
+        let &[ax, ay, bx, by, x, y] = machine else {
+            unreachable!()
+        };
+        let cheapest = (0..=100)
+            .flat_map(|a| (0..=100).map(move |b| (a, b)))
+            .filter(|(a, b)| a * ax + b * bx == x && a * ay + b * by == y)
+            .map(|(a, b)| 3 * a + b)
+            .min();
+        total += cheapest.unwrap_or(0);
+    }
+    Ok(total)
+}
+
+/// Finds the positions of the first `S` and `E` in a grid.
+fn start_end(grid: &[&[u8]]) -> Result<((usize, usize), (usize, usize))> {
+    let find = |tile| {
+        grid.iter()
+            .enumerate()
+            .find_map(|(y, row)| Some((row.iter().position(|&t| t == tile)?, y)))
+    };
+    match (find(b'S'), find(b'E')) {
+        (Some(start), Some(end)) => Ok((start, end)),
+        _ => bail!("the grid needs a start and an end"),
+    }
+}
+
+/// The neighbors of `(x, y)` within a grid of the given size.
+fn neighbors(
+    (x, y): (usize, usize),
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    [
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+    ]
+    .into_iter()
+    .filter(move |&(x, y)| x < width && y < height)
+}
+
+/// The distance from `from` to every tile reachable from it through tiles satisfying `open`.
+fn distances(
+    from: (usize, usize),
+    width: usize,
+    height: usize,
+    mut open: impl FnMut((usize, usize)) -> bool,
+    limit: usize,
+) -> HashMap<(usize, usize), usize> {
+    let mut distances = HashMap::from([(from, 0)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(xy) = queue.pop_front() {
+        let distance = distances[&xy];
+        if distance == limit {
+            continue;
+        }
+        for next in neighbors(xy, width, height) {
+            if open(next) && !distances.contains_key(&next) {
+                distances.insert(next, distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// Day 20: tries every cheat by walking through walls for up to `cheat` picoseconds from every
+/// point on the track, counting those saving at least `min_saving` picoseconds.
+pub fn day20(input: &str, cheat: usize, min_saving: usize) -> Result<u64> {
+    let grid = input.lines().map(str::as_bytes).collect::<Vec<_>>();
+    let (height, width) = (grid.len(), grid.first().map_or(0, |row| row.len()));
+    let (start, end) = start_end(&grid)?;
+    let track = |(x, y): (usize, usize)| grid[y][x] != b'#';
+
+    let from_start = distances(start, width, height, track, usize::MAX);
+    let to_end = distances(end, width, height, track, usize::MAX);
+    let Some(&best) = from_start.get(&end) else {
+        bail!("the end is not reachable")
+    };
+
+    let mut cheats = 0;
+    for (&cheat_start, &before) in &from_start {
+        let reachable = distances(cheat_start, width, height, |_| true, cheat);
+        for (cheat_end, length) in reachable {
+            if let Some(&after) = to_end.get(&cheat_end)
+                && before + length + after + min_saving <= best
+            {
+                cheats += 1;
+            }
+        }
+    }
+    Ok(cheats)
+}
+
+const NUMPAD: [&[u8]; 4] = [b"789", b"456", b"123", b" 0A"];
+const DIRPAD: [&[u8]; 2] = [b" ^A", b"<v>"];
+
+/// Day 21: finds the fewest button presses to type each code by searching over every sequence of
+/// presses, simulating each robot's arm. There are `robots` directional keypads operated by
+/// robots between the human and the numeric keypad.
+pub fn day21(input: &str, robots: usize) -> Result<u64> {
+    let mut total = 0;
+    for code in input.lines().filter(|line| !line.trim().is_empty()) {
+        let code = code.trim().as_bytes();
+        // The arm positions, starting at the numeric keypad, followed by the number of
+        // characters typed so far.
+        let initial = (
+            std::iter::once((2, 3))
+                .chain(std::iter::repeat_n((2, 0), robots))
+                .collect::<Vec<(usize, usize)>>(),
+            0,
+        );
+
+        let mut seen = HashSet::from([initial.clone()]);
+        let mut queue = VecDeque::from([(initial, 0)]);
+        let presses = loop {
+            let Some(((arms, typed), presses)) = queue.pop_front() else {
+                bail!("cannot type {}", String::from_utf8_lossy(code));
+            };
+            if typed == code.len() {
+                break presses;
+            }
+            for &button in b"^v<>A" {
+                if let Some(next) = press(&arms, typed, code, button)
+                    && seen.insert(next.clone())
+                {
+                    queue.push_back((next, presses + 1));
+                }
+            }
+        };
+
+        let number: u64 = numbers(std::str::from_utf8(code)?)?.iter().sum();
+        total += presses * number;
+    }
+    Ok(total)
+}
+
+/// Presses `button` on the human's keypad, returning the new state, or None if an arm would
+/// leave its keypad or a wrong character would be typed.
+fn press(
+    arms: &[(usize, usize)],
+    typed: usize,
+    code: &[u8],
+    mut button: u8,
+) -> Option<(Vec<(usize, usize)>, usize)> {
+    let mut arms = arms.to_vec();
+    for level in (0..arms.len()).rev() {
+        let keypad: &[&[u8]] = if level == 0 { &NUMPAD } else { &DIRPAD };
+        let (x, y) = arms[level];
+        let (x, y) = match button {
+            b'^' => (x, y.checked_sub(1)?),
+            b'v' => (x, y + 1),
+            b'<' => (x.checked_sub(1)?, y),
+            b'>' => (x + 1, y),
+            _ => {
+                // Pressing A pushes the button under this arm.
+                button = keypad[y][x];
+                continue;
+            }
+        };
+        if *keypad.get(y)?.get(x)? == b' ' {
+            return None;
+        }
+        arms[level] = (x, y);
+        return Some((arms, typed));
+    }
+
+    // The numeric keypad's button was pushed.
+    (code[typed] == button).then_some((arms, typed + 1))
+}
@@ -0,0 +1,509 @@
+//! The 3-bit computer from day 17.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail, ensure};
+
+use crate::parse::{self, Line, Parser};
+
+pub mod symbolic;
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum Instruction {
+    Adv(Combo),
+    Bxl(u8),
+    Bst(Combo),
+    Jnz(u8),
+    /// The operand is ignored, but kept so that programs can be reassembled exactly.
+    Bxc(u8),
+    Out(Combo),
+    Bdv(Combo),
+    Cdv(Combo),
+}
+
+/// A combo operand. Always in the range 0..7; operand 7 is rejected when decoding.
+#[derive(Clone, Copy)]
+pub struct Combo(u8);
+
+impl Combo {
+    pub fn apply(&self, vm: &Vm) -> u64 {
+        match self.0 {
+            n @ 0..4 => n as u64,
+            4 => vm.a,
+            5 => vm.b,
+            6 => vm.c,
+            _ => unreachable!("invalid combo operand"),
+        }
+    }
+}
+
+impl Display for Combo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            n @ 0..4 => write!(f, "{n}"),
+            4 => write!(f, "A"),
+            5 => write!(f, "B"),
+            6 => write!(f, "C"),
+            _ => unreachable!("invalid combo operand"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Fault {
+    InvalidOpcode { pc: usize, opcode: u8 },
+    InvalidOperand { pc: usize, operand: u8 },
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::InvalidOpcode { pc, opcode } => write!(f, "invalid opcode {opcode} at {pc}"),
+            Fault::InvalidOperand { pc, operand } => {
+                write!(f, "invalid combo operand {operand} at {pc}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
+
+/// Why a program stopped running.
+#[derive(Clone, Copy, Debug)]
+pub enum Stop {
+    /// The program counter moved past the end of the program.
+    Halted,
+    /// The machine returned to a state it had already been in, so it will never halt.
+    Looped {
+        pc: usize,
+        steps: u64,
+    },
+    /// The program ran for `Vm::max_steps` instructions without halting.
+    StepLimit {
+        steps: u64,
+    },
+    Fault(Fault),
+}
+
+impl Display for Stop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stop::Halted => write!(f, "program halted"),
+            Stop::Looped { pc, steps } => {
+                write!(
+                    f,
+                    "program loops forever (state at {pc} repeated after {steps} steps)"
+                )
+            }
+            Stop::StepLimit { steps } => write!(f, "program did not halt within {steps} steps"),
+            Stop::Fault(fault) => fault.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Stop {}
+
+impl From<Fault> for Stop {
+    fn from(fault: Fault) -> Self {
+        Stop::Fault(fault)
+    }
+}
+
+impl Instruction {
+    /// Decodes the instruction at `pc`, returning None if `pc` is past the end of the program.
+    pub fn decode(program: &[u8], pc: usize) -> std::result::Result<Option<Instruction>, Fault> {
+        use Instruction::*;
+        let (Some(&opcode), Some(&operand)) = (program.get(pc), program.get(pc + 1)) else {
+            return Ok(None);
+        };
+        let combo = || {
+            if operand < 7 {
+                Ok(Combo(operand))
+            } else {
+                Err(Fault::InvalidOperand { pc, operand })
+            }
+        };
+        Ok(Some(match opcode {
+            0 => Adv(combo()?),
+            1 => Bxl(operand),
+            2 => Bst(combo()?),
+            3 => Jnz(operand),
+            4 => Bxc(operand),
+            5 => Out(combo()?),
+            6 => Bdv(combo()?),
+            7 => Cdv(combo()?),
+            _ => return Err(Fault::InvalidOpcode { pc, opcode }),
+        }))
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Adv(combo) => write!(f, "adv A>>{combo}"),
+            Instruction::Bxl(op) => write!(f, "bxl B^{op}"),
+            Instruction::Bst(combo) => write!(f, "bst {combo}%8"),
+            Instruction::Jnz(op) => write!(f, "jnz {op}"),
+            Instruction::Bxc(0) => write!(f, "bxc B^C"),
+            Instruction::Bxc(op) => write!(f, "bxc B^C {op}"),
+            Instruction::Out(combo) => write!(f, "out {combo}%8"),
+            Instruction::Bdv(combo) => write!(f, "bdv B=A>>{combo}"),
+            Instruction::Cdv(combo) => write!(f, "cdv C=A>>{combo}"),
+        }
+    }
+}
+
+/// Formats a program as one instruction per line, prefixed by its address.
+pub fn disassemble(program: &[u8]) -> String {
+    let mut listing = String::new();
+    for pc in (0..program.len()).step_by(2) {
+        let line = match Instruction::decode(program, pc) {
+            Ok(Some(inst)) => inst.to_string(),
+            Ok(None) => format!(".byte {}", program[pc]),
+            Err(fault) => format!("; {fault}"),
+        };
+        listing += &format!("{pc:3}: {line}\n");
+    }
+    listing
+}
+
+#[derive(Clone)]
+pub struct Vm {
+    pub a: u64,
+    pub b: u64,
+    pub c: u64,
+
+    pub pc: usize,
+    pub program: Arc<Vec<u8>>,
+
+    /// Log every instruction executed to stderr.
+    pub trace: bool,
+
+    /// The number of instructions executed so far.
+    pub steps: u64,
+    /// Stop with `Stop::StepLimit` after executing this many instructions.
+    pub max_steps: Option<u64>,
+    /// Stop with `Stop::Looped` upon returning to an earlier state. Only states reached by a jump
+    /// are remembered, since any loop must contain one.
+    pub detect_loops: bool,
+    seen: HashSet<(usize, u64, u64, u64)>,
+}
+
+impl Vm {
+    /// Parses the register values and program from a puzzle input.
+    pub fn parse(input: impl IntoIterator<Item = Line>) -> Result<Vm> {
+        let sections = parse::sections(input);
+        let [registers, program] = &sections[..] else {
+            bail!("expected registers and a program, separated by a blank line");
+        };
+
+        let register = |i: usize, name: &str| -> Result<u64> {
+            let mut line = registers.line(i)?;
+            let value = line.field(&format!("Register {name}"), Parser::int)?;
+            line.finish()?;
+            Ok(value)
+        };
+        registers.expect_lines(3)?;
+        let (a, b, c) = (register(0, "A")?, register(1, "B")?, register(2, "C")?);
+
+        program.expect_lines(1)?;
+        let mut line = program.line(0)?;
+        let program = line.field("Program", |p| p.separated(",", Parser::int))?;
+        line.finish()?;
+
+        Ok(Vm::with_program(a, b, c, program))
+    }
+
+    pub fn with_program(a: u64, b: u64, c: u64, program: impl Into<Arc<Vec<u8>>>) -> Vm {
+        Vm {
+            a,
+            b,
+            c,
+            program: program.into(),
+            pc: 0,
+            trace: false,
+            steps: 0,
+            max_steps: None,
+            detect_loops: false,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Executes one instruction, returning its output if it has any.
+    pub fn step(&mut self) -> std::result::Result<Option<u64>, Stop> {
+        let Some(inst) = Instruction::decode(&self.program, self.pc)? else {
+            return Err(Stop::Halted);
+        };
+        if self.max_steps.is_some_and(|max| self.steps >= max) {
+            return Err(Stop::StepLimit { steps: self.steps });
+        }
+        if self.trace {
+            eprintln!(
+                "{:3}: {:<12} a={} b={} c={}",
+                self.pc,
+                inst.to_string(),
+                self.a,
+                self.b,
+                self.c
+            );
+        }
+
+        self.steps += 1;
+        self.pc += 2;
+        match inst {
+            Instruction::Adv(combo) => self.a = shr(self.a, combo.apply(self)),
+            Instruction::Bdv(combo) => self.b = shr(self.a, combo.apply(self)),
+            Instruction::Cdv(combo) => self.c = shr(self.a, combo.apply(self)),
+
+            Instruction::Bxl(op) => self.b ^= op as u64,
+            Instruction::Bst(combo) => self.b = combo.apply(self) & 7,
+            Instruction::Jnz(op) => {
+                if self.a != 0 {
+                    self.pc = op as usize;
+                    if self.detect_loops && !self.seen.insert((self.pc, self.a, self.b, self.c)) {
+                        return Err(Stop::Looped {
+                            pc: self.pc,
+                            steps: self.steps,
+                        });
+                    }
+                }
+            }
+            Instruction::Bxc(_) => self.b ^= self.c,
+            Instruction::Out(combo) => {
+                return Ok(Some(combo.apply(self) & 7));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Runs until the program stops, returning everything it output and the reason it stopped.
+    pub fn run(&mut self) -> (Vec<u64>, Stop) {
+        let mut output = Vec::new();
+        loop {
+            match self.step() {
+                Ok(None) => {}
+                Ok(Some(x)) => output.push(x),
+                Err(stop) => return (output, stop),
+            }
+        }
+    }
+}
+
+/// Shifts `a` right by `n`, which may be 64 or more (shifting everything out).
+fn shr(a: u64, n: u64) -> u64 {
+    u32::try_from(n)
+        .ok()
+        .and_then(|n| a.checked_shr(n))
+        .unwrap_or(0)
+}
+
+impl Iterator for Vm {
+    /// Never `Err(Stop::Halted)`; the iterator ends instead.
+    type Item = std::result::Result<u64, Stop>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.step() {
+                Ok(None) => continue,
+                Ok(Some(x)) => return Some(Ok(x)),
+                Err(Stop::Halted) => return None,
+                Err(stop) => return Some(Err(stop)),
+            }
+        }
+    }
+}
+
+/// Assembles mnemonic source into a program.
+///
+/// Each line holds one instruction, optionally preceded by a `label:` and followed by a comment
+/// starting with `;`. Combo operands are written as `0`-`3` or a register name `a`, `b` or `c`,
+/// and `jnz` may jump to a label. The operand syntax printed by `disassemble` (`adv A>>3`,
+/// `out B%8`) is also accepted.
+pub fn assemble(source: &str) -> Result<Vec<u8>> {
+    // First pass: find the address of each label.
+    let mut labels = HashMap::new();
+    let mut pc = 0;
+    for line in source.lines() {
+        let (label, inst) = split_label(line);
+        if let Some(label) = label {
+            labels.insert(label, pc);
+        }
+        if !inst.is_empty() {
+            pc += 2;
+        }
+    }
+
+    let mut program = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let (label, inst) = split_label(line);
+        if let Some(label) = label
+            && let Ok(address) = label.parse::<usize>()
+        {
+            // A numeric label, as printed by the disassembler, must match the actual address.
+            ensure!(
+                address == program.len(),
+                "line {}: label {label} is at address {}",
+                i + 1,
+                program.len()
+            );
+        }
+        if inst.is_empty() {
+            continue;
+        }
+        let (opcode, operand) = assemble_instruction(inst, &labels)
+            .with_context(|| format!("line {}: {inst}", i + 1))?;
+        program.extend([opcode, operand]);
+    }
+    Ok(program)
+}
+
+/// Splits a line into its label (if any) and instruction, discarding comments and whitespace.
+fn split_label(line: &str) -> (Option<&str>, &str) {
+    let line = line.split(';').next().unwrap().trim();
+    match line.split_once(':') {
+        Some((label, inst)) => (Some(label.trim()), inst.trim()),
+        None => (None, line),
+    }
+}
+
+fn assemble_instruction(inst: &str, labels: &HashMap<&str, usize>) -> Result<(u8, u8)> {
+    let (mnemonic, operand) = inst.split_once(char::is_whitespace).unwrap_or((inst, ""));
+    let operand = operand.trim();
+
+    let combo = |prefix: &str, suffix: &str| -> Result<u8> {
+        let operand = operand.strip_prefix(prefix).unwrap_or(operand);
+        let operand = operand.strip_suffix(suffix).unwrap_or(operand);
+        match operand.to_ascii_lowercase().as_str() {
+            "a" => Ok(4),
+            "b" => Ok(5),
+            "c" => Ok(6),
+            n => match n.parse() {
+                Ok(n @ 0..4) => Ok(n),
+                _ => bail!("invalid combo operand '{operand}'"),
+            },
+        }
+    };
+    let literal = |prefix: &str| -> Result<u8> {
+        let operand = operand.strip_prefix(prefix).unwrap_or(operand).trim();
+        match operand.parse() {
+            Ok(n @ 0..8) => Ok(n),
+            _ => bail!("invalid literal operand '{operand}'"),
+        }
+    };
+
+    Ok(match mnemonic.to_ascii_lowercase().as_str() {
+        "adv" => (0, combo("A>>", "")?),
+        "bxl" => (1, literal("B^")?),
+        "bst" => (2, combo("", "%8")?),
+        "jnz" => {
+            let target = match labels.get(operand) {
+                Some(&address) => address,
+                None => literal("")?.into(),
+            };
+            ensure!(target < 8, "jump target {target} out of range");
+            (3, target as u8)
+        }
+        "bxc" => match operand.strip_prefix("B^C").unwrap_or(operand).trim() {
+            "" => (4, 0),
+            _ => (4, literal("B^C")?),
+        },
+        "out" => (5, combo("", "%8")?),
+        "bdv" => (6, combo("B=A>>", "")?),
+        "cdv" => (7, combo("C=A>>", "")?),
+        _ => bail!("unknown mnemonic '{mnemonic}'"),
+    })
+}
+
+/// The shape of a program that loops over the bits of A: each iteration consumes a fixed number
+/// of bits from the bottom of A and outputs one value depending only on what remains of A.
+#[derive(Clone, Copy, Debug)]
+pub struct LoopShape {
+    pub bits_per_iteration: u32,
+}
+
+/// Checks whether `program` is a single loop over the bits of A whose iterations are independent
+/// of one another, explaining why not if it isn't.
+pub fn analyze_loop(program: &[u8]) -> Result<LoopShape> {
+    ensure!(
+        program.len() >= 2 && program.len().is_multiple_of(2),
+        "program has an odd number of bytes"
+    );
+    let end = program.len() - 2;
+
+    let mut bits = 0;
+    let mut outputs = 0;
+    // Registers B and C must be written before they are read in each iteration; otherwise an
+    // iteration's output could depend on earlier iterations.
+    let (mut b_written, mut c_written) = (false, false);
+    let read = |register: u8, pc: usize, b_written: bool, c_written: bool| -> Result<()> {
+        let (name, written) = match register {
+            5 => ("B", b_written),
+            6 => ("C", c_written),
+            _ => return Ok(()),
+        };
+        ensure!(
+            written,
+            "register {name} is read at {pc} before it is written, so each iteration depends on \
+             the one before it"
+        );
+        Ok(())
+    };
+
+    for pc in (0..program.len()).step_by(2) {
+        let inst = Instruction::decode(program, pc)?.unwrap();
+        match inst {
+            Instruction::Jnz(0) if pc == end => {}
+            Instruction::Jnz(target) => bail!(
+                "program jumps to {target} at {pc}; only a single loop ending in `jnz 0` is \
+                 supported"
+            ),
+            _ if pc == end => bail!("program does not end with `jnz 0`, so it is not a loop"),
+
+            Instruction::Adv(Combo(n @ 0..4)) => bits += n as u32,
+            Instruction::Adv(combo) => bail!(
+                "A is shifted by register {combo} at {pc}, so the number of bits consumed per \
+                 iteration isn't fixed"
+            ),
+            Instruction::Out(Combo(r)) => {
+                read(r, pc, b_written, c_written)?;
+                outputs += 1;
+            }
+            Instruction::Bdv(Combo(r)) => {
+                read(r, pc, b_written, c_written)?;
+                b_written = true;
+            }
+            Instruction::Cdv(Combo(r)) => {
+                read(r, pc, b_written, c_written)?;
+                c_written = true;
+            }
+            Instruction::Bst(Combo(r)) => {
+                read(r, pc, b_written, c_written)?;
+                b_written = true;
+            }
+            Instruction::Bxl(_) => read(5, pc, b_written, c_written)?,
+            Instruction::Bxc(_) => {
+                read(5, pc, b_written, c_written)?;
+                read(6, pc, b_written, c_written)?;
+            }
+        }
+    }
+
+    ensure!(
+        outputs == 1,
+        "program outputs {outputs} values per iteration; exactly one is supported"
+    );
+    ensure!(bits > 0, "A is never shifted, so the loop never ends");
+    ensure!(
+        bits <= 16,
+        "A is shifted by {bits} bits per iteration, which is too many to search"
+    );
+    Ok(LoopShape {
+        bits_per_iteration: bits,
+    })
+}
@@ -0,0 +1,380 @@
+//! Symbolic execution of day 17 programs, treating the initial value of A as unknown.
+//!
+//! Registers are tracked as vectors of 64 boolean expressions over the bits of A. Each path
+//! through the program yields a set of constraints (on branch conditions and on the values
+//! output), which are solved by a depth-first search over the bits of A.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+
+use super::{Combo, Instruction, Vm};
+
+/// A reference to a node in a `Circuit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Bit(u32);
+
+impl Bit {
+    const FALSE: Bit = Bit(0);
+    const TRUE: Bit = Bit(1);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Node {
+    Const(bool),
+    /// A bit of the initial value of A.
+    Var(u32),
+    Not(Bit),
+    And(Bit, Bit),
+    Or(Bit, Bit),
+    Xor(Bit, Bit),
+}
+
+type Word = [Bit; 64];
+
+/// An arena of boolean expressions. Identical expressions are shared, and every node refers only
+/// to nodes created before it, so the arena is always in topological order.
+struct Circuit {
+    nodes: Vec<Node>,
+    dedup: HashMap<Node, Bit>,
+}
+
+impl Circuit {
+    fn new() -> Self {
+        let mut circuit = Circuit {
+            nodes: Vec::new(),
+            dedup: HashMap::new(),
+        };
+        assert_eq!(circuit.node(Node::Const(false)), Bit::FALSE);
+        assert_eq!(circuit.node(Node::Const(true)), Bit::TRUE);
+        circuit
+    }
+
+    fn node(&mut self, node: Node) -> Bit {
+        *self.dedup.entry(node).or_insert_with(|| {
+            self.nodes.push(node);
+            Bit(self.nodes.len() as u32 - 1)
+        })
+    }
+
+    fn constant(&self, bit: Bit) -> Option<bool> {
+        match self.nodes[bit.0 as usize] {
+            Node::Const(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn not(&mut self, a: Bit) -> Bit {
+        match self.nodes[a.0 as usize] {
+            Node::Const(value) => self.node(Node::Const(!value)),
+            Node::Not(a) => a,
+            _ => self.node(Node::Not(a)),
+        }
+    }
+
+    fn is_negation(&self, a: Bit, b: Bit) -> bool {
+        self.nodes[a.0 as usize] == Node::Not(b) || self.nodes[b.0 as usize] == Node::Not(a)
+    }
+
+    fn and(&mut self, a: Bit, b: Bit) -> Bit {
+        match (self.constant(a), self.constant(b)) {
+            (Some(false), _) | (_, Some(false)) => Bit::FALSE,
+            (Some(true), _) => b,
+            (_, Some(true)) => a,
+            _ if a == b => a,
+            _ if self.is_negation(a, b) => Bit::FALSE,
+            _ => self.node(Node::And(a.min(b), a.max(b))),
+        }
+    }
+
+    fn or(&mut self, a: Bit, b: Bit) -> Bit {
+        match (self.constant(a), self.constant(b)) {
+            (Some(true), _) | (_, Some(true)) => Bit::TRUE,
+            (Some(false), _) => b,
+            (_, Some(false)) => a,
+            _ if a == b => a,
+            _ if self.is_negation(a, b) => Bit::TRUE,
+            _ => self.node(Node::Or(a.min(b), a.max(b))),
+        }
+    }
+
+    fn xor(&mut self, a: Bit, b: Bit) -> Bit {
+        match (self.constant(a), self.constant(b)) {
+            (Some(false), _) => b,
+            (_, Some(false)) => a,
+            (Some(true), _) => self.not(b),
+            (_, Some(true)) => self.not(a),
+            _ if a == b => Bit::FALSE,
+            _ if self.is_negation(a, b) => Bit::TRUE,
+            _ => self.node(Node::Xor(a.min(b), a.max(b))),
+        }
+    }
+
+    /// `select ? a : b`
+    fn mux(&mut self, select: Bit, a: Bit, b: Bit) -> Bit {
+        match self.constant(select) {
+            Some(true) => a,
+            Some(false) => b,
+            None if a == b => a,
+            None => {
+                let a = self.and(select, a);
+                let not_select = self.not(select);
+                let b = self.and(not_select, b);
+                self.or(a, b)
+            }
+        }
+    }
+
+    fn word(&self, value: u64) -> Word {
+        std::array::from_fn(|i| {
+            if value >> i & 1 == 1 {
+                Bit::TRUE
+            } else {
+                Bit::FALSE
+            }
+        })
+    }
+
+    fn variables(&mut self) -> Word {
+        std::array::from_fn(|i| self.node(Node::Var(i as u32)))
+    }
+
+    fn xor_words(&mut self, a: &Word, b: &Word) -> Word {
+        std::array::from_fn(|i| self.xor(a[i], b[i]))
+    }
+
+    /// The low 3 bits of `a`, with the rest cleared.
+    fn low3(&self, a: &Word) -> Word {
+        std::array::from_fn(|i| if i < 3 { a[i] } else { Bit::FALSE })
+    }
+
+    fn nonzero(&mut self, a: &Word) -> Bit {
+        a.iter().fold(Bit::FALSE, |acc, &bit| self.or(acc, bit))
+    }
+
+    /// Shifts `a` right by `amount` using a barrel shifter, producing 0 for amounts of 64 or more.
+    fn shr(&mut self, a: &Word, amount: &Word) -> Word {
+        let mut result = *a;
+        for (stage, &select) in amount.iter().enumerate().take(6) {
+            let distance = 1 << stage;
+            result = std::array::from_fn(|i| {
+                let shifted = result.get(i + distance).copied().unwrap_or(Bit::FALSE);
+                self.mux(select, shifted, result[i])
+            });
+        }
+        let overflow = self.nonzero(&std::array::from_fn(|i| {
+            if i >= 6 { amount[i] } else { Bit::FALSE }
+        }));
+        let in_range = self.not(overflow);
+        result.map(|bit| self.and(in_range, bit))
+    }
+
+    /// Evaluates every node given a partial assignment to the variables. Nodes whose value
+    /// depends on an unassigned variable evaluate to None.
+    fn evaluate(&self, vars: &[Option<bool>; 64], until: usize) -> Vec<Option<bool>> {
+        let mut values: Vec<Option<bool>> = Vec::with_capacity(until);
+        for node in &self.nodes[..until] {
+            let value = |bit: &Bit| values[bit.0 as usize];
+            let value = match node {
+                Node::Const(value) => Some(*value),
+                Node::Var(i) => vars[*i as usize],
+                Node::Not(a) => value(a).map(|a| !a),
+                Node::And(a, b) => match (value(a), value(b)) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                },
+                Node::Or(a, b) => match (value(a), value(b)) {
+                    (Some(true), _) | (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                },
+                Node::Xor(a, b) => match (value(a), value(b)) {
+                    (Some(a), Some(b)) => Some(a ^ b),
+                    _ => None,
+                },
+            };
+            values.push(value);
+        }
+        values
+    }
+
+    /// Finds the smallest value of A satisfying every constraint, assigning bits from the top
+    /// down and abandoning any partial assignment that already violates a constraint.
+    fn solve(&self, constraints: &[(Bit, bool)]) -> Option<u64> {
+        let until = constraints.iter().map(|(bit, _)| bit.0 as usize + 1).max();
+        let until = until.unwrap_or(0);
+
+        fn search(
+            circuit: &Circuit,
+            constraints: &[(Bit, bool)],
+            until: usize,
+            vars: &mut [Option<bool>; 64],
+            assigned: usize,
+        ) -> bool {
+            let values = circuit.evaluate(vars, until);
+            if constraints
+                .iter()
+                .any(|&(bit, expected)| values[bit.0 as usize] == Some(!expected))
+            {
+                return false;
+            }
+            if assigned == 64 {
+                return true;
+            }
+            let i = 63 - assigned;
+            for value in [false, true] {
+                vars[i] = Some(value);
+                if search(circuit, constraints, until, vars, assigned + 1) {
+                    return true;
+                }
+            }
+            vars[i] = None;
+            false
+        }
+
+        let mut vars = [None; 64];
+        search(self, constraints, until, &mut vars, 0).then(|| {
+            vars.iter()
+                .enumerate()
+                .map(|(i, bit)| (bit.unwrap() as u64) << i)
+                .sum()
+        })
+    }
+}
+
+/// The machine state along one path through the program.
+#[derive(Clone)]
+struct Path {
+    a: Word,
+    b: Word,
+    c: Word,
+    pc: usize,
+    outputs: usize,
+    steps: usize,
+    /// Each bit must have the given value for execution to follow this path.
+    constraints: Vec<(Bit, bool)>,
+}
+
+impl Path {
+    /// Requires `bit` to equal `value`, returning false if it is a constant that does not.
+    fn constrain(&mut self, circuit: &Circuit, bit: Bit, value: bool) -> bool {
+        match circuit.constant(bit) {
+            Some(constant) => constant == value,
+            None => {
+                self.constraints.push((bit, value));
+                true
+            }
+        }
+    }
+
+    fn combo(&self, circuit: &mut Circuit, combo: Combo) -> Word {
+        match combo.0 {
+            n @ 0..4 => circuit.word(n as u64),
+            4 => self.a,
+            5 => self.b,
+            6 => self.c,
+            _ => unreachable!("invalid combo operand"),
+        }
+    }
+}
+
+/// Finds the smallest initial value of A for which `vm` outputs exactly `target` and halts, by
+/// exploring every path through the program of at most `max_steps` instructions.
+///
+/// Registers B and C keep their values from `vm`. Returns an error if some path was cut short by
+/// the step limit, since it might have led to a smaller solution.
+pub fn invert(vm: &Vm, target: &[u64], max_steps: usize) -> Result<Option<u64>> {
+    let mut circuit = Circuit::new();
+    let start = Path {
+        a: circuit.variables(),
+        b: circuit.word(vm.b),
+        c: circuit.word(vm.c),
+        pc: vm.pc,
+        outputs: 0,
+        steps: 0,
+        constraints: Vec::new(),
+    };
+
+    let mut best: Option<u64> = None;
+    let mut truncated = false;
+    let mut paths = vec![start];
+    'paths: while let Some(mut path) = paths.pop() {
+        loop {
+            if path.steps == max_steps {
+                truncated = true;
+                continue 'paths;
+            }
+            // Paths that fault never produce the target, so they're simply abandoned.
+            let Ok(inst) = Instruction::decode(&vm.program, path.pc) else {
+                continue 'paths;
+            };
+            let Some(inst) = inst else {
+                break;
+            };
+            path.steps += 1;
+            path.pc += 2;
+
+            match inst {
+                Instruction::Adv(combo) => {
+                    let amount = path.combo(&mut circuit, combo);
+                    path.a = circuit.shr(&path.a, &amount);
+                }
+                Instruction::Bdv(combo) => {
+                    let amount = path.combo(&mut circuit, combo);
+                    path.b = circuit.shr(&path.a, &amount);
+                }
+                Instruction::Cdv(combo) => {
+                    let amount = path.combo(&mut circuit, combo);
+                    path.c = circuit.shr(&path.a, &amount);
+                }
+                Instruction::Bxl(op) => {
+                    let op = circuit.word(op as u64);
+                    path.b = circuit.xor_words(&path.b, &op);
+                }
+                Instruction::Bst(combo) => {
+                    let value = path.combo(&mut circuit, combo);
+                    path.b = circuit.low3(&value);
+                }
+                Instruction::Bxc(_) => path.b = circuit.xor_words(&path.b, &path.c),
+                Instruction::Jnz(op) => {
+                    let nonzero = circuit.nonzero(&path.a);
+                    match circuit.constant(nonzero) {
+                        Some(true) => path.pc = op as usize,
+                        Some(false) => {}
+                        None => {
+                            let mut jump = path.clone();
+                            jump.pc = op as usize;
+                            jump.constraints.push((nonzero, true));
+                            paths.push(jump);
+                            path.constraints.push((nonzero, false));
+                        }
+                    }
+                }
+                Instruction::Out(combo) => {
+                    let Some(&expected) = target.get(path.outputs).filter(|&&x| x < 8) else {
+                        continue 'paths;
+                    };
+                    let value = path.combo(&mut circuit, combo);
+                    for (i, &bit) in value.iter().take(3).enumerate() {
+                        if !path.constrain(&circuit, bit, expected >> i & 1 == 1) {
+                            continue 'paths;
+                        }
+                    }
+                    path.outputs += 1;
+                }
+            }
+        }
+
+        if path.outputs == target.len()
+            && let Some(a) = circuit.solve(&path.constraints)
+        {
+            best = Some(best.map_or(a, |best| best.min(a)));
+        }
+    }
+
+    if truncated {
+        bail!("some paths through the program run for more than {max_steps} steps");
+    }
+    Ok(best)
+}
@@ -0,0 +1,205 @@
+//! Property tests for the shared `Direction` and `Collect2d` helpers, checked against inputs from
+//! the crate's own deterministic random number generator.
+
+use aoc_2024::prelude::*;
+use aoc_2024::random::Rng;
+
+const CASES: u64 = 1000;
+
+/// Runs `property` with a differently seeded generator for each case.
+fn check(mut property: impl FnMut(&mut Rng)) {
+    for seed in 0..CASES {
+        property(&mut Rng::new(seed));
+    }
+}
+
+fn direction(rng: &mut Rng) -> Direction {
+    *rng.choose(&Direction::all())
+}
+
+/// A rotation count, sometimes huge so that the `% 4` in `cw` and `ccw` matters.
+fn turns(rng: &mut Rng) -> usize {
+    if rng.chance(0.5) {
+        rng.below(16) as usize
+    } else {
+        rng.next_u64() as usize
+    }
+}
+
+#[test]
+fn all_directions_are_distinct() {
+    let all = Direction::all();
+    assert_eq!(all.iter().unique().count(), 4);
+}
+
+#[test]
+fn cw_then_ccw_round_trips() {
+    check(|rng| {
+        let (d, n) = (direction(rng), turns(rng));
+        assert_eq!(d.cw(n).ccw(n), d, "{d:?} turned {n} times");
+        assert_eq!(d.ccw(n).cw(n), d, "{d:?} turned {n} times");
+    });
+}
+
+#[test]
+fn cw_is_repeated_quarter_turns() {
+    check(|rng| {
+        let (d, n) = (direction(rng), turns(rng));
+        let expected = (0..n % 4).fold(d, |d, _| d.cw(1));
+        assert_eq!(d.cw(n), expected, "{d:?} turned {n} times");
+        assert_eq!(d.ccw(n), d.cw(4 - n % 4), "{d:?} turned {n} times");
+    });
+}
+
+#[test]
+fn cw_follows_the_compass() {
+    use Direction::*;
+    assert_eq!(
+        [North, East, South, West].map(|d| d.cw(1)),
+        [East, South, West, North]
+    );
+    assert_eq!(
+        [North, East, South, West].map(|d| d.cw(4)),
+        [North, East, South, West]
+    );
+}
+
+#[test]
+fn invert_is_a_half_turn() {
+    check(|rng| {
+        let d = direction(rng);
+        assert_eq!(d.invert().invert(), d);
+        assert_eq!(d.invert(), d.cw(2));
+        let (dx, dy) = d.delta();
+        assert_eq!(d.invert().delta(), (-dx, -dy));
+    });
+}
+
+#[test]
+fn cw_matches_delta() {
+    // Turning clockwise in screen coordinates, where y increases downwards.
+    check(|rng| {
+        let d = direction(rng);
+        let (dx, dy) = d.delta();
+        assert_eq!(d.cw(1).delta(), (-dy, dx), "{d:?}");
+    });
+}
+
+#[test]
+fn perpendicular_is_a_quarter_turn() {
+    check(|rng| {
+        let d = direction(rng);
+        let [p, q] = d.perpendicular();
+        assert_ne!(p, q);
+        for p in [p, q] {
+            assert!(
+                p == d.cw(1) || p == d.ccw(1),
+                "{p:?} is not perpendicular to {d:?}"
+            );
+            assert_ne!(p.is_horizontal(), d.is_horizontal());
+            assert_eq!(p.is_vertical(), d.is_horizontal());
+        }
+    });
+}
+
+#[test]
+fn apply_then_invert_round_trips() {
+    check(|rng| {
+        let d = direction(rng);
+        // Stay near zero often, where `apply` can fail.
+        let mut coordinate = || {
+            if rng.chance(0.5) {
+                rng.below(3) as usize
+            } else {
+                rng.next_u64() as usize
+            }
+        };
+        let xy = (coordinate(), coordinate());
+        match d.apply(xy) {
+            Some(moved) => {
+                assert_eq!(d.invert().apply(moved), Some(xy), "{d:?} from {xy:?}");
+                let (dx, dy) = d.delta();
+                assert_eq!(
+                    (moved.0 as isize).wrapping_sub(xy.0 as isize),
+                    dx,
+                    "{d:?} from {xy:?}"
+                );
+                assert_eq!(
+                    (moved.1 as isize).wrapping_sub(xy.1 as isize),
+                    dy,
+                    "{d:?} from {xy:?}"
+                );
+            }
+            None => assert!(
+                matches!(
+                    (d, xy),
+                    (Direction::North, (_, 0))
+                        | (Direction::West, (0, _))
+                        | (Direction::South, (_, usize::MAX))
+                        | (Direction::East, (usize::MAX, _))
+                ),
+                "{d:?} from {xy:?} should not overflow"
+            ),
+        }
+    });
+}
+
+#[test]
+fn collect_2d_indexes_by_x_then_y() {
+    check(|rng| {
+        let (width, height) = (rng.below(8) as usize + 1, rng.below(8) as usize + 1);
+        let rows = (0..height)
+            .map(|_| (0..width).map(|_| rng.next_u64()).collect_vec())
+            .collect_vec();
+        let grid = rows.iter().cloned().collect_2d();
+
+        assert_eq!(grid.dim(), (width, height));
+        assert_eq!((grid.nrows(), grid.ncols()), (width, height));
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &value) in row.iter().enumerate() {
+                assert_eq!(grid[(x, y)], value, "at {:?}", (x, y));
+            }
+        }
+        for ((x, y), &value) in grid.indexed_iter() {
+            assert_eq!(rows[y][x], value, "at {:?}", (x, y));
+        }
+        assert_eq!(grid.get((width, 0)), None);
+        assert_eq!(grid.get((0, height)), None);
+    });
+}
+
+#[test]
+fn collect_2d_agrees_with_direction() {
+    check(|rng| {
+        let (width, height) = (rng.below(8) as usize + 1, rng.below(8) as usize + 1);
+        let grid = (0..height)
+            .map(|y| (0..width).map(move |x| (x, y)))
+            .collect_2d();
+        let xy = (
+            rng.below(width as u64) as usize,
+            rng.below(height as u64) as usize,
+        );
+        let d = direction(rng);
+        // Moving in a direction lands on the tile whose coordinates moved by the direction's
+        // delta, or falls off the grid.
+        let moved = d.apply(xy).and_then(|xy| grid.get(xy));
+        let (dx, dy) = d.delta();
+        let expected =
+            xy.0.checked_add_signed(dx)
+                .zip(xy.1.checked_add_signed(dy))
+                .filter(|&(x, y)| x < width && y < height);
+        assert_eq!(moved.copied(), expected);
+    });
+}
+
+#[test]
+fn collect_2d_of_nothing_is_empty() {
+    let grid = std::iter::empty::<Vec<u8>>().collect_2d();
+    assert_eq!(grid.dim(), (0, 0));
+}
+
+#[test]
+#[should_panic]
+fn collect_2d_rejects_ragged_rows() {
+    vec![vec![1, 2, 3], vec![4, 5]].into_iter().collect_2d();
+}